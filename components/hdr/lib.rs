@@ -0,0 +1,194 @@
+use std::io;
+
+/// Scanlines between these widths are run length encoded, outside of them
+/// the format only allows flat scanlines.
+const MIN_ENCODED_WIDTH: usize = 8;
+const MAX_ENCODED_WIDTH: usize = 0x7fff;
+
+/// The longest run, or the most literal bytes, a single count byte covers.
+const MAX_RUN: usize = 127;
+const MAX_LITERAL: usize = 128;
+
+/// A Radiance RGBE (`.hdr`) image writer, based off of the description
+/// found here:
+/// http://paulbourke.net/dataformats/pic/
+///
+/// Each pixel is stored as an 8-bit mantissa per channel sharing an 8-bit
+/// exponent, which keeps the full range of linear radiance values.
+pub struct HDRWriter<'a, TWrite: 'a> {
+    writer: &'a mut TWrite,
+}
+
+impl<'a, TWrite: io::Write> HDRWriter<'a, TWrite> {
+    pub fn new(writer: &'a mut TWrite) -> Self {
+        HDRWriter {
+            writer,
+        }
+    }
+
+    /// Writes `width * height` linear RGB triples, starting at the top left.
+    /// Negative and NaN samples are written as 0.0, infinite ones and those
+    /// too bright for the format as the brightest pixel it can hold.
+    pub fn write(&mut self, image_data: &[f32], width: usize, height: usize) -> io::Result<()> {
+        if image_data.len() != width * height * 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("expected {} samples for a {}x{} image, found {}",
+                        width * height * 3, width, height, image_data.len())));
+        }
+
+        write!(self.writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+
+        if width == 0 {
+            return Ok(());
+        }
+
+        let mut scanline = Vec::with_capacity(width * 4);
+        for row in image_data.chunks(width * 3) {
+            scanline.clear();
+            for pixel in row.chunks(3) {
+                scanline.extend_from_slice(&rgbe(pixel[0], pixel[1], pixel[2]));
+            }
+
+            if (MIN_ENCODED_WIDTH..=MAX_ENCODED_WIDTH).contains(&width) {
+                self.write_encoded_scanline(&scanline, width)?;
+            } else {
+                self.writer.write_all(&scanline)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a scanline with each of the four components run length
+    /// encoded separately.
+    fn write_encoded_scanline(&mut self, scanline: &[u8], width: usize) -> io::Result<()> {
+        let mut bytes = vec![2, 2, (width >> 8) as u8, width as u8];
+
+        for component in 0..4 {
+            let values: Vec<u8> = scanline.iter().skip(component).step_by(4).cloned().collect();
+            encode_component(&values, &mut bytes);
+        }
+
+        self.writer.write_all(&bytes)
+    }
+}
+
+/// The largest exponent the exponent byte can hold, `128` is added to it.
+const MAX_EXPONENT: i32 = 127;
+
+/// Converts a linear colour to a shared exponent RGBE pixel.
+pub fn rgbe(r: f32, g: f32, b: f32) -> [u8; 4] {
+    // NaN fails the comparison and becomes 0.0
+    let positive = |sample: f32| if sample > 0.0 { sample } else { 0.0 };
+    let (r, g, b) = (positive(r), positive(g), positive(b));
+
+    let brightest = r.max(g).max(b);
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // Infinity and anything from 2^127 up needs a larger exponent than
+    // there is, so they saturate
+    if !brightest.is_finite() {
+        return [255, 255, 255, (MAX_EXPONENT + 128) as u8];
+    }
+
+    let exponent = (brightest.log2().floor() as i32 + 1).max(-128);
+    if exponent > MAX_EXPONENT {
+        return [255, 255, 255, (MAX_EXPONENT + 128) as u8];
+    }
+
+    let scale = 256.0 / 2f32.powi(exponent);
+
+    [(r * scale).min(255.0) as u8, (g * scale).min(255.0) as u8, (b * scale).min(255.0) as u8, (exponent + 128) as u8]
+}
+
+/// Encodes one component of a scanline as a series of runs, a count byte
+/// above 128 followed by the repeated value, and literals, a count byte
+/// followed by that many values.
+fn encode_component(values: &[u8], bytes: &mut Vec<u8>) {
+    let mut position = 0;
+
+    while position < values.len() {
+        let run_length = run_length_at(values, position);
+        if run_length >= 4 {
+            bytes.push((128 + run_length) as u8);
+            bytes.push(values[position]);
+            position += run_length;
+            continue;
+        }
+
+        // Collect literals until the next run worth encoding
+        let start = position;
+        while position < values.len() && position - start < MAX_LITERAL && run_length_at(values, position) < 4 {
+            position += 1;
+        }
+
+        bytes.push((position - start) as u8);
+        bytes.extend_from_slice(&values[start..position]);
+    }
+}
+
+fn run_length_at(values: &[u8], position: usize) -> usize {
+    values[position..].iter()
+        .take(MAX_RUN)
+        .take_while(|&&value| value == values[position])
+        .count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rgbe() {
+        assert_eq!(rgbe(0.0, 0.0, 0.0), [0, 0, 0, 0]);
+        assert_eq!(rgbe(1.0, 0.5, 0.25), [128, 64, 32, 129]);
+        assert_eq!(rgbe(-1.0, f32::NAN, 8.0), [0, 0, 128, 132]);
+    }
+
+    #[test]
+    fn test_rgbe_out_of_range() {
+        assert_eq!(rgbe(f32::INFINITY, 0.0, 0.0), [255, 255, 255, 255]);
+        assert_eq!(rgbe(1.0, f32::NEG_INFINITY, f32::INFINITY), [255, 255, 255, 255]);
+        assert_eq!(rgbe(f32::NAN, f32::NAN, f32::NAN), [0, 0, 0, 0]);
+        assert_eq!(rgbe(f32::MAX, 1.0, 1.0), [255, 255, 255, 255]);
+        assert_eq!(rgbe(2f32.powi(127), 0.0, 0.0), [255, 255, 255, 255]);
+
+        // The largest value below that still fits
+        assert_eq!(rgbe(2f32.powi(126), 0.0, 0.0), [128, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_write_flat() {
+        let mut out = Vec::new();
+        HDRWriter::new(&mut out).write(&[1.0, 0.5, 0.25, 0.0, 0.0, 0.0], 2, 1).unwrap();
+
+        let mut expected = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        expected.extend_from_slice(&[128, 64, 32, 129, 0, 0, 0, 0]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_write_encoded() {
+        let mut image_data = vec![1.0; 10 * 3];
+        image_data[0] = 0.5;
+
+        let mut out = Vec::new();
+        HDRWriter::new(&mut out).write(&image_data, 10, 1).unwrap();
+
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 10\n".len();
+        assert_eq!(&out[header..], &[2, 2, 0, 10,
+                                     1, 64, 128 + 9, 128,
+                                     128 + 10, 128,
+                                     128 + 10, 128,
+                                     128 + 10, 129][..]);
+    }
+
+    #[test]
+    fn test_wrong_size() {
+        let mut out = Vec::new();
+        let error = HDRWriter::new(&mut out).write(&[0.0; 3], 2, 1).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,42 +1,265 @@
 use std::io;
 
+mod pfm;
+mod reader;
+
+pub use pfm::*;
+pub use reader::*;
+
+/// The number of bits used to store each sample.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BitDepth {
+    /// Samples from 0 to 255
+    Eight,
+
+    /// Samples from 0 to 65535, written most significant byte first in
+    /// binary images
+    Sixteen,
+}
+
+impl BitDepth {
+    pub fn max_value(self) -> u32 {
+        match self {
+            BitDepth::Eight => 255,
+            BitDepth::Sixteen => 65535,
+        }
+    }
+}
+
+/// How the image data is encoded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PPMFormat {
+    /// The plain `P3` format, samples are written as ASCII decimal numbers
+    Ascii(BitDepth),
+
+    /// The raw `P6` format, samples are written as binary
+    Binary(BitDepth),
+}
+
+impl PPMFormat {
+    pub fn bit_depth(self) -> BitDepth {
+        match self {
+            PPMFormat::Ascii(bit_depth) | PPMFormat::Binary(bit_depth) => bit_depth,
+        }
+    }
+}
+
+/// What the writer does with samples outside of 0.0 to 1.0, or which are
+/// not a number.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PixelPolicy {
+    /// Clamp samples into range, NaN is written as 0.0
+    Clamp,
+
+    /// Fail with an `InvalidData` error before anything is written
+    Error,
+
+    /// Clamp samples into range and record the location of every pixel
+    /// containing a NaN, see `PPMWriter::nan_pixels`
+    ReportNan,
+}
+
 /// A PPM image writer based off of the spec found here:
 /// http://netpbm.sourceforge.net/doc/ppm.html
 pub struct PPMWriter<'a, TWrite: 'a> {
-    writer: &'a mut TWrite
+    writer: &'a mut TWrite,
+    format: PPMFormat,
+    pixel_policy: PixelPolicy,
+    nan_pixels: Vec<(usize, usize)>,
 }
 
 impl<'a, TWrite: io::Write> PPMWriter<'a, TWrite> {
+    /// Creates a writer for plain 8-bit `P3` images.
     pub fn new(writer: &'a mut TWrite) -> Self {
+        PPMWriter::with_format(writer, PPMFormat::Ascii(BitDepth::Eight))
+    }
+
+    pub fn with_format(writer: &'a mut TWrite, format: PPMFormat) -> Self {
         PPMWriter {
-            writer: writer
+            writer,
+            format,
+            pixel_policy: PixelPolicy::Clamp,
+            nan_pixels: Vec::new(),
         }
     }
 
+    pub fn set_pixel_policy(&mut self, pixel_policy: PixelPolicy) {
+        self.pixel_policy = pixel_policy;
+    }
+
+    /// The `(x, y)` location of each pixel containing a NaN in the last
+    /// image written with `PixelPolicy::ReportNan`, starting at the top left.
+    pub fn nan_pixels(&self) -> &[(usize, usize)] {
+        &self.nan_pixels
+    }
+
+    /// Writes `width * height` RGB triples, starting at the top left.
+    ///
+    /// Nothing is written if `image_data` is the wrong size, or if it has
+    /// invalid samples under `PixelPolicy::Error`.
     pub fn write(&mut self, image_data: &[f32], width: usize, height: usize) -> io::Result<()> {
-        try!(self.write_magic_number());
-        try!(self.write_metadata(width, height, 255));
+        if image_data.len() != width * height * 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("expected {} samples for a {}x{} image, found {}",
+                        width * height * 3, width, height, image_data.len())));
+        }
+
+        let samples = self.quantize(image_data, width)?;
+
+        self.write_magic_number()?;
+        self.write_metadata(width, height, self.format.bit_depth().max_value())?;
+
+        self.write_image_data(&samples)
+    }
 
-        self.write_image_data(image_data)
+    /// Converts each sample to an integer from 0 to the maximum value,
+    /// applying the pixel policy.
+    fn quantize(&mut self, image_data: &[f32], width: usize) -> io::Result<Vec<u16>> {
+        let max_value = self.format.bit_depth().max_value() as f32;
+        let mut samples = Vec::with_capacity(image_data.len());
+        self.nan_pixels.clear();
+
+        for (index, &sample) in image_data.iter().enumerate() {
+            let valid = (0.0..=1.0).contains(&sample);
+            if !valid && self.pixel_policy == PixelPolicy::Error {
+                let pixel = index / 3;
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("sample {} at pixel ({}, {}) is outside of 0.0 to 1.0", sample, pixel % width, pixel / width)));
+            }
+
+            if sample.is_nan() && self.pixel_policy == PixelPolicy::ReportNan {
+                let pixel = index / 3;
+                let location = (pixel % width, pixel / width);
+                if self.nan_pixels.last() != Some(&location) {
+                    self.nan_pixels.push(location);
+                }
+            }
+
+            // NaN fails both comparisons and becomes 0.0
+            let clamped = if sample > 1.0 { 1.0 } else if sample > 0.0 { sample } else { 0.0 };
+            samples.push((max_value * clamped) as u16);
+        }
+
+        Ok(samples)
     }
 
     fn write_magic_number(&mut self) -> io::Result<()> {
-        write!(&mut self.writer, "P3\n")
+        match self.format {
+            PPMFormat::Ascii(_) => writeln!(&mut self.writer, "P3"),
+            PPMFormat::Binary(_) => writeln!(&mut self.writer, "P6"),
+        }
     }
 
     fn write_metadata(&mut self, width: usize, height: usize, max_pixel_val: u32) -> io::Result<()> {
         write!(&mut self.writer, "{} {}\n{}\n", width, height, max_pixel_val)
     }
 
-    fn write_image_data(&mut self, image_data: &[f32]) -> io::Result<()> {
-        for pixel in image_data.chunks(3) {
-            let r = (255.0 * pixel[0]) as u8;
-            let g = (255.0 * pixel[1]) as u8;
-            let b = (255.0 * pixel[2]) as u8;
+    fn write_image_data(&mut self, samples: &[u16]) -> io::Result<()> {
+        match self.format {
+            PPMFormat::Ascii(_) => {
+                for pixel in samples.chunks(3) {
+                    write!(self.writer, "{} {} {} ", pixel[0], pixel[1], pixel[2])?;
+                }
+
+                Ok(())
+            }
+            PPMFormat::Binary(BitDepth::Eight) => {
+                let bytes: Vec<u8> = samples.iter().map(|&sample| sample as u8).collect();
+                self.writer.write_all(&bytes)
+            }
+            PPMFormat::Binary(BitDepth::Sixteen) => {
+                let mut bytes = Vec::with_capacity(samples.len() * 2);
+                for &sample in samples {
+                    bytes.push((sample >> 8) as u8);
+                    bytes.push(sample as u8);
+                }
+
+                self.writer.write_all(&bytes)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
-            try!(write!(self.writer, "{} {} {} ", r, g, b));
+    fn write(format: PPMFormat) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let mut writer = PPMWriter::with_format(&mut out, format);
+            writer.write(&[1.0, 0.5, 0.0, 0.0, 0.0, 1.0], 2, 1).unwrap();
         }
+        out
+    }
+
+    #[test]
+    fn test_write_ascii() {
+        assert_eq!(write(PPMFormat::Ascii(BitDepth::Eight)), b"P3\n2 1\n255\n255 127 0 0 0 255 ".to_vec());
+        assert_eq!(write(PPMFormat::Ascii(BitDepth::Sixteen)), b"P3\n2 1\n65535\n65535 32767 0 0 0 65535 ".to_vec());
+    }
+
+    #[test]
+    fn test_write_binary() {
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 127, 0, 0, 0, 255]);
+        assert_eq!(write(PPMFormat::Binary(BitDepth::Eight)), expected);
 
-        Ok(())
+        let mut expected = b"P6\n2 1\n65535\n".to_vec();
+        expected.extend_from_slice(&[0xff, 0xff, 0x7f, 0xff, 0, 0, 0, 0, 0, 0, 0xff, 0xff]);
+        assert_eq!(write(PPMFormat::Binary(BitDepth::Sixteen)), expected);
+    }
+
+    #[test]
+    fn test_clamp() {
+        let mut out = Vec::new();
+        PPMWriter::new(&mut out).write(&[2.0, -1.0, f32::NAN], 1, 1).unwrap();
+        assert_eq!(out, b"P3\n1 1\n255\n255 0 0 ".to_vec());
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let mut out = Vec::new();
+        {
+            let mut writer = PPMWriter::new(&mut out);
+            writer.set_pixel_policy(PixelPolicy::Error);
+            let error = writer.write(&[0.5, 1.5, 0.5], 1, 1).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_report_nan() {
+        let mut out = Vec::new();
+        let mut writer = PPMWriter::new(&mut out);
+        writer.set_pixel_policy(PixelPolicy::ReportNan);
+
+        let nan = f32::NAN;
+        writer.write(&[0.0, 0.0, 0.0, nan, nan, 0.0, 0.0, 0.0, 0.0, 0.0, nan, 0.0], 2, 2).unwrap();
+        assert_eq!(writer.nan_pixels(), &[(1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn test_wrong_size() {
+        let mut out = Vec::new();
+        let error = PPMWriter::new(&mut out).write(&[0.0; 9], 2, 2).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_read_round_trip() {
+        let formats = [PPMFormat::Ascii(BitDepth::Eight), PPMFormat::Ascii(BitDepth::Sixteen),
+                       PPMFormat::Binary(BitDepth::Eight), PPMFormat::Binary(BitDepth::Sixteen)];
+
+        for &format in formats.iter() {
+            let bytes = write(format);
+            let image = PPMReader::new(&mut &bytes[..]).read().unwrap();
+
+            let max_value = format.bit_depth().max_value() as f32;
+            assert_eq!((image.width, image.height, image.channels), (2, 1, 3));
+            assert_eq!(image.data, vec![1.0, (max_value * 0.5) as u16 as f32 * (1.0 / max_value), 0.0, 0.0, 0.0, 1.0]);
+        }
     }
 }
@@ -0,0 +1,84 @@
+use std::io;
+
+/// A portable float map writer based off of the spec found here:
+/// http://netpbm.sourceforge.net/doc/pfm.html
+///
+/// Samples are written unmodified as little endian 32-bit floats, so linear
+/// radiance values above 1.0 are kept.
+pub struct PFMWriter<'a, TWrite: 'a> {
+    writer: &'a mut TWrite,
+    channels: usize,
+}
+
+impl<'a, TWrite: io::Write> PFMWriter<'a, TWrite> {
+    /// Creates a writer for colour (`PF`) images, three samples per pixel.
+    pub fn new(writer: &'a mut TWrite) -> Self {
+        PFMWriter {
+            writer,
+            channels: 3,
+        }
+    }
+
+    /// Creates a writer for greyscale (`Pf`) images, one sample per pixel.
+    pub fn greyscale(writer: &'a mut TWrite) -> Self {
+        PFMWriter {
+            writer,
+            channels: 1,
+        }
+    }
+
+    /// Writes `width * height` pixels, starting at the top left.
+    pub fn write(&mut self, image_data: &[f32], width: usize, height: usize) -> io::Result<()> {
+        let row_length = width * self.channels;
+        if image_data.len() != row_length * height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("expected {} samples for a {}x{} image, found {}",
+                        row_length * height, width, height, image_data.len())));
+        }
+
+        let magic_number = if self.channels == 3 { "PF" } else { "Pf" };
+
+        // A negative scale marks the samples as little endian
+        write!(self.writer, "{}\n{} {}\n-1.0\n", magic_number, width, height)?;
+
+        if row_length == 0 {
+            return Ok(());
+        }
+
+        // Rows are stored from the bottom of the image up
+        let mut bytes = Vec::with_capacity(image_data.len() * 4);
+        for row in image_data.chunks(row_length).rev() {
+            for sample in row {
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+
+        self.writer.write_all(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reader::PPMReader;
+
+    #[test]
+    fn test_float_map_round_trip() {
+        let image_data = [0.0, 1.5, -2.0, 100.0, 0.25, 3.0];
+
+        let mut out = Vec::new();
+        PFMWriter::new(&mut out).write(&image_data, 1, 2).unwrap();
+        assert!(out.starts_with(b"PF\n1 2\n-1.0\n"));
+
+        let image = PPMReader::new(&mut &out[..]).read().unwrap();
+        assert_eq!((image.width, image.height, image.channels), (1, 2, 3));
+        assert_eq!(image.data, image_data.to_vec());
+
+        let mut out = Vec::new();
+        PFMWriter::greyscale(&mut out).write(&image_data, 3, 2).unwrap();
+
+        let image = PPMReader::new(&mut &out[..]).read().unwrap();
+        assert_eq!((image.width, image.height, image.channels), (3, 2, 1));
+        assert_eq!(image.data, image_data.to_vec());
+    }
+}
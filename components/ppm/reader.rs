@@ -0,0 +1,332 @@
+use std::io;
+
+/// A decoded image.
+///
+/// `data` holds `width * height * channels` samples starting at the top
+/// left of the image. Samples read from the integer formats are scaled to
+/// 0.0 to 1.0, floating point samples are returned as they were stored.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub data: Vec<f32>,
+}
+
+impl Image {
+    /// The samples of the pixel at `(x, y)`, counting from the top left.
+    pub fn pixel(&self, x: usize, y: usize) -> &[f32] {
+        let start = (y * self.width + x) * self.channels;
+        &self.data[start..start + self.channels]
+    }
+}
+
+/// A reader for the netpbm formats `P1` to `P6`, along with the portable
+/// float map formats `PF` (colour) and `Pf` (greyscale).
+///
+/// http://netpbm.sourceforge.net/doc/pbm.html
+/// http://netpbm.sourceforge.net/doc/pgm.html
+/// http://netpbm.sourceforge.net/doc/ppm.html
+/// http://netpbm.sourceforge.net/doc/pfm.html
+pub struct PPMReader<'a, TRead: 'a> {
+    reader: &'a mut TRead,
+}
+
+impl<'a, TRead: io::Read> PPMReader<'a, TRead> {
+    pub fn new(reader: &'a mut TRead) -> Self {
+        PPMReader {
+            reader,
+        }
+    }
+
+    pub fn read(&mut self) -> io::Result<Image> {
+        let mut bytes = Vec::new();
+        self.reader.read_to_end(&mut bytes)?;
+
+        let mut parser = Parser { bytes: &bytes, position: 0 };
+        let magic = parser.take(2)?;
+        if magic[0] != b'P' {
+            return Err(invalid_data("not a netpbm or pfm image"));
+        }
+
+        match magic[1] {
+            b'1' => parser.read_bitmap(false),
+            b'4' => parser.read_bitmap(true),
+            b'2' => parser.read_integer_samples(1, false),
+            b'5' => parser.read_integer_samples(1, true),
+            b'3' => parser.read_integer_samples(3, false),
+            b'6' => parser.read_integer_samples(3, true),
+            b'F' => parser.read_float_map(3),
+            b'f' => parser.read_float_map(1),
+            _ => Err(invalid_data("unsupported image format")),
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Multiplies out the size of a raster, which is too big for any file if it
+/// overflows.
+fn raster_size(factors: &[usize]) -> io::Result<usize> {
+    factors.iter().try_fold(1usize, |size, &factor| size.checked_mul(factor))
+        .ok_or_else(|| invalid_data("image dimensions are too large"))
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() - self.position < count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "image data ended early"));
+        }
+
+        let taken = &self.bytes[self.position..self.position + count];
+        self.position += count;
+        Ok(taken)
+    }
+
+    /// How much to reserve for `count` samples of a plain image. The header
+    /// can claim anything, but every sample takes at least a byte.
+    fn capacity(&self, count: usize) -> usize {
+        count.min(self.bytes.len() - self.position)
+    }
+
+    /// Skips whitespace and comments, which run from `#` to the end of the
+    /// line.
+    fn skip_whitespace(&mut self) {
+        while self.position < self.bytes.len() {
+            match self.bytes[self.position] {
+                b'#' => {
+                    while self.position < self.bytes.len() && self.bytes[self.position] != b'\n' {
+                        self.position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn token(&mut self) -> io::Result<&'a str> {
+        self.skip_whitespace();
+
+        let start = self.position;
+        while self.position < self.bytes.len() && !self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "image data ended early"));
+        }
+
+        ::std::str::from_utf8(&self.bytes[start..self.position]).map_err(|_| invalid_data("invalid header"))
+    }
+
+    fn number<T: ::std::str::FromStr>(&mut self) -> io::Result<T> {
+        self.token()?.parse().map_err(|_| invalid_data("invalid number"))
+    }
+
+    /// Raster data starts after a single whitespace character.
+    fn end_of_header(&mut self) -> io::Result<()> {
+        let byte = self.take(1)?[0];
+        if byte.is_ascii_whitespace() {
+            Ok(())
+        } else {
+            Err(invalid_data("expected whitespace before the raster"))
+        }
+    }
+
+    fn dimensions(&mut self) -> io::Result<(usize, usize)> {
+        let width = self.number()?;
+        let height = self.number()?;
+        Ok((width, height))
+    }
+
+    /// Reads a `P1` or `P4` bitmap, where 1 is black.
+    fn read_bitmap(&mut self, binary: bool) -> io::Result<Image> {
+        let (width, height) = self.dimensions()?;
+        let count = raster_size(&[width, height])?;
+
+        if binary {
+            self.end_of_header()?;
+
+            // Each row is padded to a whole number of bytes
+            let row_bytes = width.div_ceil(8);
+            let raster = self.take(raster_size(&[row_bytes, height])?)?;
+            let mut data = Vec::with_capacity(count);
+
+            for row in raster.chunks(row_bytes.max(1)) {
+                for x in 0..width {
+                    let bit = (row[x / 8] >> (7 - x % 8)) & 1;
+                    data.push(1.0 - bit as f32);
+                }
+            }
+
+            Ok(Image { width, height, channels: 1, data })
+        } else {
+            let mut data = Vec::with_capacity(self.capacity(count));
+
+            // Plain bitmaps don't need whitespace between the digits
+            while data.len() < count {
+                self.skip_whitespace();
+                match self.take(1)?[0] {
+                    b'0' => data.push(1.0),
+                    b'1' => data.push(0.0),
+                    _ => return Err(invalid_data("invalid bitmap digit")),
+                }
+            }
+
+            Ok(Image { width, height, channels: 1, data })
+        }
+    }
+
+    /// Reads a `P2`, `P3`, `P5` or `P6` image.
+    fn read_integer_samples(&mut self, channels: usize, binary: bool) -> io::Result<Image> {
+        let (width, height) = self.dimensions()?;
+        let max_value: u32 = self.number()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid_data("max value must be between 1 and 65535"));
+        }
+
+        let count = raster_size(&[width, height, channels])?;
+        let scale = 1.0 / max_value as f32;
+
+        let samples: Vec<u32> = if binary {
+            self.end_of_header()?;
+
+            if max_value < 256 {
+                self.take(count)?.iter().map(|&sample| sample as u32).collect()
+            } else {
+                self.take(raster_size(&[count, 2])?)?.chunks(2)
+                    .map(|sample| (sample[0] as u32) << 8 | sample[1] as u32)
+                    .collect()
+            }
+        } else {
+            let mut samples = Vec::with_capacity(self.capacity(count));
+            for _ in 0..count {
+                samples.push(self.number()?);
+            }
+            samples
+        };
+
+        if samples.iter().any(|&sample| sample > max_value) {
+            return Err(invalid_data("sample is greater than the max value"));
+        }
+
+        let data = samples.iter().map(|&sample| sample as f32 * scale).collect();
+
+        Ok(Image { width, height, channels, data })
+    }
+
+    /// Reads a `PF` or `Pf` float map. A negative scale marks little endian
+    /// samples, and rows are stored from the bottom of the image up.
+    fn read_float_map(&mut self, channels: usize) -> io::Result<Image> {
+        let (width, height) = self.dimensions()?;
+        let scale: f32 = self.number()?;
+        if scale == 0.0 || scale.is_nan() {
+            return Err(invalid_data("invalid float map scale"));
+        }
+
+        self.end_of_header()?;
+
+        let row_length = raster_size(&[width, channels])?;
+        let raster = self.take(raster_size(&[row_length, height, 4])?)?;
+        if raster.is_empty() {
+            return Ok(Image { width, height, channels, data: Vec::new() });
+        }
+
+        let mut data = Vec::with_capacity(raster.len() / 4);
+        for row in raster.chunks(row_length * 4).rev() {
+            data.extend(row.chunks(4).map(|sample| {
+                let bytes = [sample[0], sample[1], sample[2], sample[3]];
+                if scale < 0.0 { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }
+            }));
+        }
+
+        Ok(Image { width, height, channels, data })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_greyscale_and_bitmaps() {
+        let plain_bitmap = b"P1\n# a comment\n3 2\n010\n1 1 0\n";
+        let image = PPMReader::new(&mut &plain_bitmap[..]).read().unwrap();
+        assert_eq!(image.channels, 1);
+        assert_eq!(image.data, vec![1.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+
+        let raw_bitmap = [b'P', b'4', b'\n', b'3', b' ', b'2', b'\n', 0b0100_0000, 0b1100_0000];
+        let image = PPMReader::new(&mut &raw_bitmap[..]).read().unwrap();
+        assert_eq!(image.data, vec![1.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+
+        let plain_greymap = b"P2 2 1 4 0 4";
+        let image = PPMReader::new(&mut &plain_greymap[..]).read().unwrap();
+        assert_eq!(image.data, vec![0.0, 1.0]);
+
+        let raw_greymap = [b'P', b'5', b' ', b'2', b' ', b'1', b' ', b'2', b'5', b'5', b'\n', 255, 0];
+        let image = PPMReader::new(&mut &raw_greymap[..]).read().unwrap();
+        assert_eq!(image.pixel(0, 0), &[1.0]);
+        assert_eq!(image.pixel(1, 0), &[0.0]);
+    }
+
+    #[test]
+    fn test_read_float_map() {
+        // Little endian, two rows stored bottom first
+        let mut bytes = b"Pf\n1 2\n-1.0\n".to_vec();
+        bytes.extend_from_slice(&2.5f32.to_le_bytes());
+        bytes.extend_from_slice(&(-1.0f32).to_le_bytes());
+
+        let image = PPMReader::new(&mut &bytes[..]).read().unwrap();
+        assert_eq!((image.width, image.height, image.channels), (1, 2, 1));
+        assert_eq!(image.data, vec![-1.0, 2.5]);
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(PPMReader::new(&mut &b"P7 1 1 255\n"[..]).read().is_err());
+        assert!(PPMReader::new(&mut &b"P3 2 1 255\n0 0 0"[..]).read().is_err());
+        assert!(PPMReader::new(&mut &b"P6 1 1 255\n\x00"[..]).read().is_err());
+    }
+
+    #[test]
+    fn test_read_sample_above_max_value() {
+        for bytes in &[&b"P3 1 1 255 300 0 0"[..], b"P2 2 1 4 0 5", b"P5 1 1 4\n\x05",
+                       b"P6 1 1 1000\n\x03\xe9\x00\x00\x00\x00"] {
+            let error = PPMReader::new(&mut &bytes[..]).read().err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // The max value itself is fine
+        let image = PPMReader::new(&mut &b"P6 1 1 1000\n\x03\xe8\x00\x00\x00\x00"[..]).read().unwrap();
+        assert_eq!(image.data, vec![1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_read_empty() {
+        for header in &[&b"PF 0 5 -1.0\n"[..], b"Pf 3 0 -1.0\n", b"P4 0 5\n", b"P6 0 2 255\n", b"P3 2 0 255\n", b"P1 0 0\n"] {
+            let image = PPMReader::new(&mut &header[..]).read().unwrap();
+            assert!(image.data.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_oversized_header() {
+        // Dimensions whose size overflows, then ones far bigger than the data
+        let huge = usize::MAX / 2;
+        for size in &[format!("{} {}", huge, huge), "100000 100000".to_string()] {
+            for header in &[format!("P1 {}\n0 0", size), format!("P4 {}\n0 0", size),
+                            format!("P2 {} 255\n0 0", size), format!("P5 {} 255\n0 0", size),
+                            format!("P3 {} 255\n0 0", size), format!("P6 {} 65535\n0 0", size),
+                            format!("PF {} -1.0\n0 0", size), format!("Pf {} -1.0\n0 0", size)] {
+                assert!(PPMReader::new(&mut header.as_bytes()).read().is_err(), "{}", header);
+            }
+        }
+    }
+}
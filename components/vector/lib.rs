@@ -1,5 +1,11 @@
 use std::ops;
 
+mod matrix;
+mod quaternion;
+
+pub use matrix::*;
+pub use quaternion::*;
+
 /// A four-dimensional homogenous Vector. (4xf32)
 #[derive(Copy, Clone)]
 pub struct Vector4 {
@@ -70,6 +76,22 @@ impl Vector4 {
     }
 }
 
+impl ops::Index<usize> for Vector4 {
+    type Output = f32;
+
+    /// Access a component by index, 0 to 3 for x, y, z and w.
+    #[inline]
+    fn index(&self, index: usize) -> &f32 {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            3 => &self.w,
+            _ => panic!("Vector4 index out of range: {}", index),
+        }
+    }
+}
+
 impl ops::Mul<f32> for Vector4 {
     type Output = Self;
 
@@ -243,6 +265,16 @@ impl ops::Neg for Vector4 {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_index() {
+        let a = Vector4::new(10.0, 20.0, 30.0, 40.0);
+
+        assert_eq!(a[0], 10.0);
+        assert_eq!(a[1], 20.0);
+        assert_eq!(a[2], 30.0);
+        assert_eq!(a[3], 40.0);
+    }
+
     #[test]
     fn test_vector_multiply() {
         let a = Vector4::new(10.0, 20.0, 30.0, 40.0);
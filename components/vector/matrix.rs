@@ -0,0 +1,375 @@
+use std::ops;
+use Vector4;
+
+/// A 4x4 matrix of f32s, stored by rows, for transforming `Vector4`s as
+/// column vectors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix4 {
+    pub rows: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    /// Create a matrix from its rows
+    pub fn new(rows: [[f32; 4]; 4]) -> Self {
+        Matrix4 {
+            rows,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Moves points by `offset`, directions are unaffected
+    pub fn translation(offset: Vector4) -> Self {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Scales each axis by the matching component of `factors`
+    pub fn scale(factors: Vector4) -> Self {
+        Matrix4::new([
+            [factors.x, 0.0, 0.0, 0.0],
+            [0.0, factors.y, 0.0, 0.0],
+            [0.0, 0.0, factors.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotates anticlockwise by `angle` radians about `axis`, looking back
+    /// down the axis towards the origin
+    pub fn rotation(axis: Vector4, angle: f32) -> Self {
+        let axis = Vector4::new3(axis.x, axis.y, axis.z).unit_vector();
+        let (x, y, z) = (axis.x, axis.y, axis.z);
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+
+        Matrix4::new([
+            [t * x * x + cos,     t * x * y - sin * z, t * x * z + sin * y, 0.0],
+            [t * x * y + sin * z, t * y * y + cos,     t * y * z - sin * x, 0.0],
+            [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos,     0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Shears each axis in proportion to the others, e.g. `xy` is how far x
+    /// moves for each unit of y
+    pub fn shear(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Self {
+        Matrix4::new([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A view transform for a camera at `eye` looking towards `target`,
+    /// mapping world space to a space where the camera sits at the origin
+    /// looking down -z with `up` towards +y
+    pub fn look_at(eye: Vector4, target: Vector4, up: Vector4) -> Self {
+        let w = Vector4::new3(eye.x - target.x, eye.y - target.y, eye.z - target.z).unit_vector();
+        let u = up.cross(w).unit_vector();
+        let v = w.cross(u);
+        let eye = Vector4::new3(eye.x, eye.y, eye.z);
+
+        Matrix4::new([
+            [u.x, u.y, u.z, -u.dot3(eye)],
+            [v.x, v.y, v.z, -v.dot3(eye)],
+            [w.x, w.y, w.z, -w.dot3(eye)],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A perspective projection of the view space from `look_at` into clip
+    /// space, with a vertical field of view of `vertical_fov` radians. The
+    /// `near` and `far` planes map to z of -1 and 1 after dividing by `w`
+    pub fn perspective(vertical_fov: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let focal_length = 1.0 / (vertical_fov / 2.0).tan();
+
+        Matrix4::new([
+            [focal_length / aspect, 0.0, 0.0, 0.0],
+            [0.0, focal_length, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / (near - far), 2.0 * far * near / (near - far)],
+            [0.0, 0.0, -1.0, 0.0],
+        ])
+    }
+
+    /// Calculates the determinant by cofactor expansion along the first row
+    pub fn determinant(&self) -> f32 {
+        let m = &self.rows;
+
+        // The 2x2 determinants of the bottom two rows
+        let minor = |a: usize, b: usize| m[2][a] * m[3][b] - m[2][b] * m[3][a];
+
+        let cofactor0 = m[1][1] * minor(2, 3) - m[1][2] * minor(1, 3) + m[1][3] * minor(1, 2);
+        let cofactor1 = m[1][0] * minor(2, 3) - m[1][2] * minor(0, 3) + m[1][3] * minor(0, 2);
+        let cofactor2 = m[1][0] * minor(1, 3) - m[1][1] * minor(0, 3) + m[1][3] * minor(0, 1);
+        let cofactor3 = m[1][0] * minor(1, 2) - m[1][1] * minor(0, 2) + m[1][2] * minor(0, 1);
+
+        m[0][0] * cofactor0 - m[0][1] * cofactor1 + m[0][2] * cofactor2 - m[0][3] * cofactor3
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Matrix4::identity();
+        for i in 0..4 {
+            for j in 0..4 {
+                result.rows[i][j] = self.rows[j][i];
+            }
+        }
+        result
+    }
+
+    /// Calculates the inverse by Gauss-Jordan elimination, or `None` if the
+    /// matrix is singular or has an entry which isn't finite
+    pub fn inverse(&self) -> Option<Self> {
+        let mut left = self.rows;
+        let mut right = Matrix4::identity().rows;
+
+        for column in 0..4 {
+            // Partial pivoting, swap up the row with the largest entry
+            let pivot = (column..4)
+                .max_by(|&a, &b| left[a][column].abs().total_cmp(&left[b][column].abs()))
+                .unwrap();
+
+            // A NaN or infinite entry sorts above every finite one
+            if left[pivot][column].abs() < 1e-12 || !left[pivot][column].is_finite() {
+                return None;
+            }
+
+            left.swap(column, pivot);
+            right.swap(column, pivot);
+
+            let scale = 1.0 / left[column][column];
+            for j in 0..4 {
+                left[column][j] *= scale;
+                right[column][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row != column {
+                    let factor = left[row][column];
+                    for j in 0..4 {
+                        left[row][j] -= factor * left[column][j];
+                        right[row][j] -= factor * right[column][j];
+                    }
+                }
+            }
+        }
+
+        Some(Matrix4::new(right))
+    }
+
+    /// Transforms the 3D point `point`, as if its `w` were 1, dividing
+    /// through by the transformed `w` for projections. The result is a 3D
+    /// vector as from `Vector4::new3`
+    pub fn transform_point(&self, point: Vector4) -> Vector4 {
+        let r = &self.rows;
+        let result = Vector4::new3(
+            r[0][0] * point.x + r[0][1] * point.y + r[0][2] * point.z + r[0][3],
+            r[1][0] * point.x + r[1][1] * point.y + r[1][2] * point.z + r[1][3],
+            r[2][0] * point.x + r[2][1] * point.y + r[2][2] * point.z + r[2][3],
+        );
+        let w = r[3][0] * point.x + r[3][1] * point.y + r[3][2] * point.z + r[3][3];
+
+        if w == 1.0 { result } else { result / w }
+    }
+
+    /// Transforms the 3D direction `direction`, as if its `w` were 0, so
+    /// translation has no effect
+    pub fn transform_vector(&self, direction: Vector4) -> Vector4 {
+        let r = &self.rows;
+        Vector4::new3(
+            r[0][0] * direction.x + r[0][1] * direction.y + r[0][2] * direction.z,
+            r[1][0] * direction.x + r[1][1] * direction.y + r[1][2] * direction.z,
+            r[2][0] * direction.x + r[2][1] * direction.y + r[2][2] * direction.z,
+        )
+    }
+}
+
+impl ops::Mul<Matrix4> for Matrix4 {
+    type Output = Self;
+
+    /// The matrix applying `rhs` first, then `self`
+    fn mul(self, rhs: Matrix4) -> Self::Output {
+        let mut result = [[0.0; 4]; 4];
+        for (i, row) in result.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        Matrix4::new(result)
+    }
+}
+
+impl ops::Mul<Vector4> for Matrix4 {
+    type Output = Vector4;
+
+    /// Transforms a homogeneous vector, using its `w` as given. Points
+    /// should have a `w` of 1 and directions a `w` of 0
+    fn mul(self, rhs: Vector4) -> Self::Output {
+        let row = |i: usize| {
+            let r = self.rows[i];
+            r[0] * rhs.x + r[1] * rhs.y + r[2] * rhs.z + r[3] * rhs.w
+        };
+
+        Vector4::new(row(0), row(1), row(2), row(3))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: Vector4, b: Vector4) {
+        assert!((a - b).length() < 1e-5, "({}, {}, {}) != ({}, {}, {})", a.x, a.y, a.z, b.x, b.y, b.z);
+    }
+
+    #[test]
+    fn test_transform_point_and_vector() {
+        let transform = Matrix4::translation(Vector4::new3(1.0, 2.0, 3.0)) * Matrix4::scale(Vector4::new3(2.0, 2.0, 2.0));
+
+        let result = transform.transform_point(Vector4::new3(1.0, 1.0, 1.0));
+        assert_eq!(result.x, 3.0);
+        assert_eq!(result.y, 4.0);
+        assert_eq!(result.z, 5.0);
+
+        let result = transform.transform_vector(Vector4::new3(1.0, 1.0, 1.0));
+        assert_eq!(result.x, 2.0);
+        assert_eq!(result.y, 2.0);
+        assert_eq!(result.z, 2.0);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let rotation = Matrix4::rotation(Vector4::new3(0.0, 0.0, 1.0), ::std::f32::consts::FRAC_PI_2);
+        assert_near(rotation.transform_vector(Vector4::new3(1.0, 0.0, 0.0)), Vector4::new3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_inverse() {
+        let transform = Matrix4::translation(Vector4::new3(1.0, -2.0, 3.0)) *
+                        Matrix4::rotation(Vector4::new3(1.0, 1.0, 0.0), 0.7) *
+                        Matrix4::shear(0.5, 0.0, 0.0, 0.25, 0.0, 0.0) *
+                        Matrix4::scale(Vector4::new3(2.0, 3.0, 4.0));
+        let inverse = transform.inverse().unwrap();
+
+        let point = Vector4::new3(0.5, 1.5, -2.5);
+        assert_near(inverse.transform_point(transform.transform_point(point)), point);
+
+        let product = transform * inverse;
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product.rows[i][j] - expected).abs() < 1e-5);
+            }
+        }
+
+        assert!(Matrix4::scale(Vector4::new3(1.0, 0.0, 1.0)).inverse().is_none());
+
+        for &value in &[f32::NAN, f32::INFINITY] {
+            let mut rows = Matrix4::identity().rows;
+            rows[1][2] = value;
+            assert!(Matrix4::new(rows).inverse().is_none());
+
+            assert!(Matrix4::scale(Vector4::new3(1.0, value, 1.0)).inverse().is_none());
+        }
+    }
+
+    #[test]
+    fn test_matrix_multiply() {
+        let a = Matrix4::new([
+            [1.0, 2.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        assert_eq!(a * Matrix4::identity(), a);
+        assert_eq!(Matrix4::identity() * a, a);
+
+        let result = a * a;
+        assert_eq!(result.rows[0][1], 4.0);
+        assert_eq!(result.rows[2][3], 6.0);
+    }
+
+    #[test]
+    fn test_vector_multiply_respects_w() {
+        let transform = Matrix4::translation(Vector4::new3(1.0, 2.0, 3.0));
+
+        let point = transform * Vector4::new(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(point.x, 2.0);
+        assert_eq!(point.y, 3.0);
+        assert_eq!(point.z, 4.0);
+        assert_eq!(point.w, 1.0);
+
+        let direction = transform * Vector4::new(1.0, 1.0, 1.0, 0.0);
+        assert_eq!(direction.x, 1.0);
+        assert_eq!(direction.y, 1.0);
+        assert_eq!(direction.z, 1.0);
+        assert_eq!(direction.w, 0.0);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let a = Matrix4::translation(Vector4::new3(1.0, 2.0, 3.0)).transpose();
+        assert_eq!(a.rows[3][0], 1.0);
+        assert_eq!(a.rows[3][1], 2.0);
+        assert_eq!(a.rows[3][2], 3.0);
+        assert_eq!(a.rows[0][3], 0.0);
+    }
+
+    #[test]
+    fn test_determinant() {
+        assert_eq!(Matrix4::identity().determinant(), 1.0);
+        assert_eq!(Matrix4::scale(Vector4::new3(2.0, 3.0, 4.0)).determinant(), 24.0);
+        assert_eq!(Matrix4::translation(Vector4::new3(5.0, 6.0, 7.0)).determinant(), 1.0);
+
+        let a = Matrix4::new([
+            [2.0, 0.0, 1.0, 3.0],
+            [1.0, 4.0, 0.0, 2.0],
+            [0.0, 3.0, 1.0, 1.0],
+            [1.0, 0.0, 2.0, 1.0],
+        ]);
+        assert_eq!(a.determinant(), -13.0);
+
+        // A rotation keeps volumes, a mirror turns them inside out
+        assert!((Matrix4::rotation(Vector4::new3(1.0, 2.0, 3.0), 1.2).determinant() - 1.0).abs() < 1e-5);
+        assert_eq!(Matrix4::scale(Vector4::new3(-1.0, 1.0, 1.0)).determinant(), -1.0);
+    }
+
+    #[test]
+    fn test_look_at() {
+        let eye = Vector4::new3(0.0, 0.0, 5.0);
+        let view = Matrix4::look_at(eye, Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 1.0, 0.0));
+
+        assert_near(view.transform_point(eye), Vector4::new3(0.0, 0.0, 0.0));
+        assert_near(view.transform_point(Vector4::new3(0.0, 0.0, 0.0)), Vector4::new3(0.0, 0.0, -5.0));
+        assert_near(view.transform_vector(Vector4::new3(0.0, 1.0, 0.0)), Vector4::new3(0.0, 1.0, 0.0));
+        assert_near(view.transform_vector(Vector4::new3(1.0, 0.0, 0.0)), Vector4::new3(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_perspective() {
+        let projection = Matrix4::perspective(::std::f32::consts::FRAC_PI_2, 2.0, 1.0, 10.0);
+
+        assert_near(projection.transform_point(Vector4::new3(0.0, 0.0, -1.0)), Vector4::new3(0.0, 0.0, -1.0));
+        assert_near(projection.transform_point(Vector4::new3(0.0, 0.0, -10.0)), Vector4::new3(0.0, 0.0, 1.0));
+
+        // The top and right edges of the view at the near plane
+        assert_near(projection.transform_point(Vector4::new3(2.0, 1.0, -1.0)), Vector4::new3(1.0, 1.0, -1.0));
+
+        // Further away things appear smaller
+        let far = projection.transform_point(Vector4::new3(2.0, 1.0, -4.0));
+        assert!((far.x - 0.25).abs() < 1e-5);
+        assert!((far.y - 0.25).abs() < 1e-5);
+    }
+}
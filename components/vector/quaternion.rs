@@ -0,0 +1,221 @@
+use std::ops;
+use { Matrix4, Vector4 };
+
+/// A quaternion `w + xi + yj + zk`. Unit quaternions represent rotations,
+/// and can be interpolated smoothly with `slerp`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quaternion {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Quaternion {
+            x,
+            y,
+            z,
+            w,
+        }
+    }
+
+    /// The rotation which leaves everything where it is
+    pub fn identity() -> Self {
+        Quaternion::new(0.0, 0.0, 0.0, 1.0)
+    }
+
+    /// Rotates anticlockwise by `angle` radians about `axis`, the same as
+    /// `Matrix4::rotation`
+    pub fn from_axis_angle(axis: Vector4, angle: f32) -> Self {
+        let axis = Vector4::new3(axis.x, axis.y, axis.z).unit_vector();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+
+        Quaternion::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+    }
+
+    /// Rotates by `x` radians about the x axis, then `y` about the y axis,
+    /// and finally `z` about the z axis
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Self {
+        Quaternion::from_axis_angle(Vector4::new3(0.0, 0.0, 1.0), z) *
+        Quaternion::from_axis_angle(Vector4::new3(0.0, 1.0, 0.0), y) *
+        Quaternion::from_axis_angle(Vector4::new3(1.0, 0.0, 0.0), x)
+    }
+
+    pub fn dot(self, rhs: Quaternion) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    /// Scales to unit length, correcting the drift from composing many
+    /// rotations
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        Quaternion::new(self.x / length, self.y / length, self.z / length, self.w / length)
+    }
+
+    /// The inverse rotation of a unit quaternion
+    pub fn conjugate(self) -> Self {
+        Quaternion::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    /// Rotates the 3D part of `vector` by this unit quaternion, keeping its
+    /// `w` as it was
+    pub fn rotate(self, vector: Vector4) -> Vector4 {
+        let axis = Vector4::new3(self.x, self.y, self.z);
+        let v = Vector4::new3(vector.x, vector.y, vector.z);
+
+        // v + 2w(q × v) + 2q × (q × v), expanded from q v q*
+        let t = axis.cross(v) * 2.0;
+        let rotated = v + t * self.w + axis.cross(t);
+
+        Vector4::new(rotated.x, rotated.y, rotated.z, vector.w)
+    }
+
+    /// The rotation matrix of this unit quaternion
+    pub fn to_matrix(self) -> Matrix4 {
+        let (x, y, z, w) = (self.x, self.y, self.z, self.w);
+
+        Matrix4::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z),       2.0 * (x * z + w * y),       0.0],
+            [2.0 * (x * y + w * z),       1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x),       0.0],
+            [2.0 * (x * z - w * y),       2.0 * (y * z + w * x),       1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Spherical linear interpolation between two unit quaternions, turning
+    /// at a constant rate the short way round from `self` at `t` = 0 to
+    /// `other` at `t` = 1
+    pub fn slerp(self, other: Quaternion, t: f32) -> Self {
+        // q and -q are the same rotation, pick whichever is nearer
+        let mut cos_theta = self.dot(other);
+        let other = if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            other * -1.0
+        } else {
+            other
+        };
+
+        // Nearly parallel, where sin(theta) would divide by almost zero
+        if cos_theta > 0.9995 {
+            return (self * (1.0 - t) + other * t).normalize();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+
+        self * a + other * b
+    }
+}
+
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Self;
+
+    /// The Hamilton product, the rotation applying `rhs` first, then `self`
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        Quaternion::new(
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+        )
+    }
+}
+
+impl ops::Mul<f32> for Quaternion {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Quaternion::new(self.x * rhs, self.y * rhs, self.z * rhs, self.w * rhs)
+    }
+}
+
+impl ops::Add<Quaternion> for Quaternion {
+    type Output = Self;
+
+    fn add(self, rhs: Quaternion) -> Self::Output {
+        Quaternion::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z, self.w + rhs.w)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_near(a: Vector4, b: Vector4) {
+        assert!((a - b).length() < 1e-5, "({}, {}, {}) != ({}, {}, {})", a.x, a.y, a.z, b.x, b.y, b.z);
+    }
+
+    #[test]
+    fn test_rotate() {
+        let q = Quaternion::from_axis_angle(Vector4::new3(0.0, 0.0, 1.0), FRAC_PI_2);
+
+        let result = q.rotate(Vector4::new(1.0, 0.0, 0.0, 1.0));
+        assert_near(result, Vector4::new(0.0, 1.0, 0.0, 1.0));
+        assert_eq!(result.w, 1.0);
+
+        assert_near(q.conjugate().rotate(result), Vector4::new(1.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_to_matrix() {
+        let axis = Vector4::new3(1.0, 2.0, 3.0);
+        let q = Quaternion::from_axis_angle(axis, 0.8);
+        let matrix = Matrix4::rotation(axis, 0.8);
+
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((q.to_matrix().rows[i][j] - matrix.rows[i][j]).abs() < 1e-5);
+            }
+        }
+
+        let v = Vector4::new3(0.3, -1.0, 2.0);
+        assert_near(q.rotate(v), matrix.transform_vector(v));
+    }
+
+    #[test]
+    fn test_euler() {
+        // A quarter turn about x takes y to z, then a quarter turn about z
+        // takes the untouched x to y
+        let q = Quaternion::from_euler(FRAC_PI_2, 0.0, FRAC_PI_2);
+        assert_near(q.rotate(Vector4::new3(0.0, 1.0, 0.0)), Vector4::new3(0.0, 0.0, 1.0));
+        assert_near(q.rotate(Vector4::new3(1.0, 0.0, 0.0)), Vector4::new3(0.0, 1.0, 0.0));
+
+        let identity = Quaternion::from_euler(0.0, 0.0, 0.0);
+        assert_eq!(identity, Quaternion::identity());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let q = Quaternion::new(1.0, 2.0, 2.0, 4.0).normalize();
+        assert!((q.length() - 1.0).abs() < 1e-6);
+        assert_eq!(q.x, 0.2);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let axis = Vector4::new3(0.0, 1.0, 0.0);
+        let start = Quaternion::identity();
+        let end = Quaternion::from_axis_angle(axis, FRAC_PI_2);
+
+        let halfway = start.slerp(end, 0.5);
+        let expected = Quaternion::from_axis_angle(axis, FRAC_PI_2 / 2.0);
+        assert!((halfway.dot(expected) - 1.0).abs() < 1e-5);
+        assert!((halfway.length() - 1.0).abs() < 1e-5);
+
+        assert!((start.slerp(end, 0.0).dot(start) - 1.0).abs() < 1e-5);
+        assert!((start.slerp(end, 1.0).dot(end) - 1.0).abs() < 1e-5);
+
+        // Goes the short way round even when the end is negated
+        let negated = end * -1.0;
+        assert!((start.slerp(negated, 0.5).dot(expected).abs() - 1.0).abs() < 1e-5);
+    }
+}
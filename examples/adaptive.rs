@@ -0,0 +1,66 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::env;
+use std::fs::File;
+use std::io::{ self, BufWriter };
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// Renders a glass sphere and its caustic with adaptive sampling, writing the
+// image to stdout and a heatmap of where the samples went to the given file:
+//
+//   cargo run --release --example adaptive -- heatmap.ppm > adaptive.ppm
+fn main() {
+    let width  = 400;
+    let height = 200;
+    let max_samples = 512;
+
+    let heatmap_path = env::args().nth(1).expect("usage: adaptive <heatmap.ppm>");
+
+    let mut renderer = renderer(width, height);
+    renderer.adaptive_sampling = Some(AdaptiveSampling::new(16, max_samples, 0.02));
+
+    let mut framebuffer = Framebuffer::new(width, height);
+    renderer.render_progressive(&scene()[..], &mut framebuffer, |framebuffer, progress| {
+        if let Progress::Pass { pass } = progress {
+            let total: u64 = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| framebuffer.sample_count(x, y) as u64).sum();
+            eprintln!("pass {} done, {:.1} samples per pixel", pass + 1, total as f64 / (width * height) as f64);
+        }
+    });
+
+    let mut heatmap = BufWriter::new(File::create(&heatmap_path).unwrap());
+    PPMWriter::new(&mut heatmap).write(&framebuffer.sample_heatmap(max_samples as u32)[..], width, height).unwrap();
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    PPMWriter::new(&mut out).write(&framebuffer.image(renderer.gamma)[..], width, height).unwrap();
+}
+
+fn scene() -> Vec<Box<dyn Renderable>> {
+    vec![
+        Box::new(Plane::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 1.0, 0.0), Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))),
+        Box::new(Sphere::new(Vector4::new3(0.0, 1.0, 0.0), 1.0, Dielectric::new(1.5))),
+        Box::new(Sphere::new(Vector4::new3(-2.5, 1.0, -1.0), 1.0, Lambertian::new(Vector4::new3(0.8, 0.3, 0.3)))),
+        Box::new(Sphere::new(Vector4::new3(0.0, 7.0, 0.0), 1.5, DiffuseLight::new(Vector4::new3(8.0, 8.0, 8.0)))),
+    ]
+}
+
+fn renderer(width: usize, height: usize) -> Renderer {
+    let look_from = Vector4::new3(6.0, 3.0, 6.0);
+    let look_at   = Vector4::new3(-0.5, 0.7, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 30.0,
+                    width as f32 / height as f32, 0.0,
+                    (look_from - look_at).length());
+
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pass = 16;
+    renderer.background = Background::Solid(Vector4::new3(0.05, 0.05, 0.08));
+    renderer
+}
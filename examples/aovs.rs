@@ -0,0 +1,53 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// Renders the chapter 11 scene along with its depth, normal, albedo and
+// object ID passes, each written to its own file in the given directory:
+//
+//   cargo run --example aovs -- out/
+fn main() {
+    let width  = 200;
+    let height = 100;
+
+    let directory = env::args().nth(1).expect("usage: aovs <output directory>");
+    let directory = Path::new(&directory);
+
+    let (image_data, aovs) = generate_image_data(width, height);
+
+    let mut out = BufWriter::new(File::create(directory.join("beauty.ppm")).unwrap());
+    PPMWriter::new(&mut out).write(&image_data[..], width, height).unwrap();
+
+    aovs.save(directory).unwrap();
+}
+
+fn generate_image_data(width: usize, height: usize) -> (Vec<f32>, Aovs) {
+    let look_from = Vector4::new3(3.0, 3.0, 2.0);
+    let look_at   = Vector4::new3(0.0, 0.0, -1.0);
+    let dist_to_focus = (look_from - look_at).length();
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 20.0,
+                    width as f32 / height as f32, 0.5,
+                    dist_to_focus);
+
+    let renderable_list: Vec<Box<dyn Renderable>> = vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, Lambertian::new(Vector4::new3(0.8, 0.3, 0.3)))),
+        Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, Lambertian::new(Vector4::new3(0.8, 0.8, 0.0)))),
+        Box::new(Sphere::new(Vector4::new3(1.0, 0.0, -1.0), 0.5, Metal::new(Vector4::new3(0.8, 0.6, 0.2), 0.3))),
+        Box::new(Sphere::new(Vector4::new3(-1.0, 0.0, -1.0), 0.5, Dielectric::new(1.5))),
+    ];
+
+    let renderer = Renderer::new(camera, width, height);
+
+    renderer.render_with_aovs(&renderable_list[..])
+}
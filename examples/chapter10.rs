@@ -1,15 +1,12 @@
 extern crate ppm;
-extern crate rand;
 extern crate vector;
 extern crate weekendraytracer;
 
 use std::io;
 use ppm::PPMWriter;
-use rand::Rng;
 use vector::Vector4;
 
 use weekendraytracer::*;
-use std::f32::consts::PI;
 
 fn main() {
     let width  = 200;
@@ -26,13 +23,11 @@ fn main() {
 }
 
 fn generate_image_data(width: usize, height: usize, anti_alias_sample_size: usize) -> Vec<f32> {
-    let mut image_data = Vec::new();
-
     let camera = Camera::new(
                     Vector4::new3(-2.0, 2.0, 1.0), Vector4::new3(0.0, 0.0, -1.0),
-                    Vector4::new3(0.0, 1.0, 0.0), 35.0, width as f32 / height as f32);
+                    Vector4::new3(0.0, 1.0, 0.0), 35.0, width as f32 / height as f32, 0.0, 1.0);
 
-    let mut renderable_list: Vec<Box<Renderable>> = Vec::new();
+    let mut renderable_list: Vec<Box<dyn Renderable>> = Vec::new();
 
     let lambertian_a = Lambertian::new(Vector4::new3(0.8, 0.3, 0.3));
     let lambertian_b = Lambertian::new(Vector4::new3(0.8, 0.8, 0.0));
@@ -46,58 +41,8 @@ fn generate_image_data(width: usize, height: usize, anti_alias_sample_size: usiz
     renderable_list.push(Box::new(Sphere::new(Vector4::new3(1.0, 0.0, -1.0), 0.5, metal_a)));
     renderable_list.push(Box::new(Sphere::new(Vector4::new3(-1.0, 0.0, -1.0), -0.5, dielectric)));
 
-    let mut rng = rand::thread_rng();
-
-    // Traverses the screen space from the left to right, starting at
-    // the bottom left
-    for y in (0..height).rev() {
-        for x in 0..width {
-            let mut anti_aliased_colour = Vector4::new3(0.0, 0.0, 0.0);
-            for _ in 0..anti_alias_sample_size {
-                let u = (x as f32 + rng.next_f32()) / width as f32;
-                let v = (y as f32 + rng.next_f32()) / height as f32;
-
-                let ray = camera.get_ray(u, v);
-                anti_aliased_colour += colour(&ray, &renderable_list[..], 0);
-            }
-
-            let col = anti_aliased_colour / (anti_alias_sample_size as f32);
-
-            let r = col.x;
-            let g = col.y;
-            let b = col.z;
-            image_data.push(r.sqrt());
-            image_data.push(g.sqrt());
-            image_data.push(b.sqrt());
-        }
-    }
-
-    image_data
-}
-
-
-// Linearly blends white and blue depending on the y value of the target
-// screen coordinate
-fn colour(ray: &Ray, renderable_list: &[Box<Renderable>], depth: u32) -> Vector4 {
-    if let Some(renderable_intersection) = renderable_list.intersects(ray, 0.001, std::f32::MAX) {
-
-        if depth <= 50 {
-            if let Some((scattered, attenuation)) = renderable_intersection.material.scatter(ray, &renderable_intersection) {
-                return attenuation * colour(&scattered, renderable_list, depth + 1);
-            }
-        }
-
-        Vector4::new3(0.0, 0.0, 0.0)
-    } else {
-
-        // Make unit so (-1.0 < y < 1.0) holds true
-        let unit_direction = ray.direction().unit_vector();
-
-        // Scale to 0.0 < t < 1.0
-        let t = 0.5 * (unit_direction.y + 1.0);
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pixel = anti_alias_sample_size;
 
-        // Linear interpolation (lerp) of white (1.0, 1.0, 1.0) and blue (0.5, 0.7, 1.0)
-        //   blended_value = (1 - t) * start_value + t * end_value
-        (Vector4::new3(1.0, 1.0, 1.0) * (1.0 - t)) + (Vector4::new3(0.5, 0.7, 1.0) * t)
-    }
+    renderer.render(&renderable_list[..])
 }
@@ -24,8 +24,11 @@ fn main() {
     writer.write(&image_data[..], width, height).unwrap();
 }
 
-fn random_scene() -> Vec<Box<Renderable>> {
-    let mut renderable_list: Vec<Box<Renderable>> = Vec::new();
+fn random_scene() -> Vec<Box<dyn Renderable>> {
+    let mut renderable_list: Vec<Box<dyn Renderable>> = Vec::new();
+
+    // A fixed seed gives the same scene every run
+    let mut rng = random::seeded_rng(0);
 
     renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, -1000.0, 0.0), 1000.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))));
 
@@ -34,20 +37,20 @@ fn random_scene() -> Vec<Box<Renderable>> {
         for b in -11..11 {
             let f_b = b as f32;
 
-            let choose_mat = rand::random::<f32>();
+            let choose_mat = rng.next_f32();
             let center = Vector4::new3(
-                f_a + 0.9 * rand::random::<f32>(),
+                f_a + 0.9 * rng.next_f32(),
                 0.2,
-                f_b + 0.9 * rand::random::<f32>()
+                f_b + 0.9 * rng.next_f32()
             );
 
             if (center - Vector4::new3(4.0, 0.2, 0.0)).length() > 0.9 {
                 if choose_mat < 0.8 { //diffuse
                     let mat = Lambertian::new(
                         Vector4::new3(
-                            rand::random::<f32>(),
-                            rand::random::<f32>(),
-                            rand::random::<f32>()
+                            rng.next_f32(),
+                            rng.next_f32(),
+                            rng.next_f32()
                         )
                     );
 
@@ -55,11 +58,11 @@ fn random_scene() -> Vec<Box<Renderable>> {
                 } else if choose_mat < 0.95 { // metal
                     let mat = Metal::new(
                         Vector4::new3(
-                            1.0 + rand::random::<f32>(),
-                            1.0 + rand::random::<f32>(),
-                            1.0 + rand::random::<f32>()
+                            1.0 + rng.next_f32(),
+                            1.0 + rng.next_f32(),
+                            1.0 + rng.next_f32()
                         ) * 0.5,
-                        rand::random::<f32>() * 0.5
+                        rng.next_f32() * 0.5
                     );
                     renderable_list.push(Box::new(Sphere::new(center, 0.2, mat)));
                 } else { // glass
@@ -82,8 +85,6 @@ fn random_scene() -> Vec<Box<Renderable>> {
 }
 
 fn generate_image_data(width: usize, height: usize, anti_alias_sample_size: usize) -> Vec<f32> {
-    let mut image_data = Vec::new();
-
     let look_from = Vector4::new3(20.0 * 0.47_f32.cos(), 20.0 * 0.47_f32, 3.0);
     let look_at   = Vector4::new3(0.0, 0.0, 1.0);
     let dist_to_focus = (look_from - look_at).length();
@@ -94,60 +95,10 @@ fn generate_image_data(width: usize, height: usize, anti_alias_sample_size: usiz
                     width as f32 / height as f32, aperture,
                     dist_to_focus);
 
-    let renderable_list = random_scene();
-
-    let mut rng = rand::thread_rng();
-
-    // Traverses the screen space from the left to right, starting at
-    // the bottom left
-    for y in (0..height).rev() {
-        for x in 0..width {
-            let mut anti_aliased_colour = Vector4::new3(0.0, 0.0, 0.0);
-            for _ in 0..anti_alias_sample_size {
-                let u = (x as f32 + rng.next_f32()) / width as f32;
-                let v = (y as f32 + rng.next_f32()) / height as f32;
-
-                let ray = camera.get_ray(u, v);
-                anti_aliased_colour += colour(&ray, &renderable_list[..], 0);
-            }
-
-            let col = anti_aliased_colour / (anti_alias_sample_size as f32);
-
-            let r = col.x;
-            let g = col.y;
-            let b = col.z;
-            image_data.push(r.sqrt());
-            image_data.push(g.sqrt());
-            image_data.push(b.sqrt());
-        }
-    }
-
-    image_data
-}
-
-
-// Linearly blends white and blue depending on the y value of the target
-// screen coordinate
-fn colour(ray: &Ray, renderable_list: &[Box<Renderable>], depth: u32) -> Vector4 {
-    if let Some(renderable_intersection) = renderable_list.intersects(ray, 0.001, std::f32::MAX) {
-
-        if depth <= 50 {
-            if let Some((scattered, attenuation)) = renderable_intersection.material.scatter(ray, &renderable_intersection) {
-                return attenuation * colour(&scattered, renderable_list, depth + 1);
-            }
-        }
-
-        Vector4::new3(0.0, 0.0, 0.0)
-    } else {
+    let scene = Bvh::new(random_scene());
 
-        // Make unit so (-1.0 < y < 1.0) holds true
-        let unit_direction = ray.direction().unit_vector();
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pixel = anti_alias_sample_size;
 
-        // Scale to 0.0 < t < 1.0
-        let t = 0.5 * (unit_direction.y + 1.0);
-
-        // Linear interpolation (lerp) of white (1.0, 1.0, 1.0) and blue (0.5, 0.7, 1.0)
-        //   blended_value = (1 - t) * start_value + t * end_value
-        (Vector4::new3(1.0, 1.0, 1.0) * (1.0 - t)) + (Vector4::new3(0.5, 0.7, 1.0) * t)
-    }
+    renderer.render(&scene)
 }
@@ -107,7 +107,7 @@ fn hit_sphere(center: Vector4, radius: f32, ray: &Ray) -> bool {
     let c = (o_minus_c).dot3(o_minus_c) - radius * radius;
 
     let discriminant = b * b - a * c * 4.0;
-    return discriminant > 0.0;
+    discriminant > 0.0
 }
 
 // Linearly blends white and blue depending on the y value of the target
@@ -1,4 +1,5 @@
 extern crate ppm;
+extern crate rand;
 extern crate vector;
 extern crate weekendraytracer;
 
@@ -6,7 +7,7 @@ use std::io;
 use ppm::PPMWriter;
 use vector::Vector4;
 
-use weekendraytracer::{ Sphere, Ray, Renderable };
+use weekendraytracer::{ Lambertian, Sphere, Ray, Renderable };
 
 fn main() {
     let width  = 200;
@@ -29,10 +30,12 @@ fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
     let vertical   = Vector4::new3(0.0, 2.0, 0.0);
     let origin     = Vector4::new3(0.0, 0.0, 0.0);
 
-    let mut renderable_list: Vec<Box<Renderable>> = Vec::new();
+    let mut renderable_list: Vec<Box<dyn Renderable>> = Vec::new();
 
-    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5)));
-    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0)));
+    // Only the normals are shaded, the material is never used
+    let grey = Lambertian::new(Vector4::new3(0.5, 0.5, 0.5));
+    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, grey.clone())));
+    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, grey)));
 
     // Traverses the screen space from the left to right, starting at
     // the bottom left
@@ -61,8 +64,8 @@ fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
 
 // Linearly blends white and blue depending on the y value of the target
 // screen coordinate
-fn colour(ray: &Ray, renderable_list: &[Box<Renderable>]) -> Vector4 {
-    if let Some(renderable_intersection) = renderable_list.intersects(ray, 0.0, std::f32::MAX) {
+fn colour(ray: &Ray, renderable_list: &[Box<dyn Renderable>]) -> Vector4 {
+    if let Some(renderable_intersection) = renderable_list.intersects(ray, 0.0, f32::MAX, &mut rand::thread_rng()) {
         return (renderable_intersection.normal + 1.0) * 0.5;
     }
 
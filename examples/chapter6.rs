@@ -8,7 +8,7 @@ use ppm::PPMWriter;
 use rand::Rng;
 use vector::Vector4;
 
-use weekendraytracer::{ Camera, Sphere, Ray, Renderable };
+use weekendraytracer::{ Camera, IntersectionRecord, Material, Sphere, Ray, Renderable, Renderer };
 
 fn main() {
     let width  = 200;
@@ -25,59 +25,32 @@ fn main() {
 }
 
 fn generate_image_data(width: usize, height: usize, anti_alias_sample_size: usize) -> Vec<f32> {
-    let mut image_data = Vec::new();
-
-    let camera = Camera::new();
-    let mut renderable_list: Vec<Box<Renderable>> = Vec::new();
-
-    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5)));
-    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0)));
-
-    let mut rng = rand::thread_rng();
-
-    // Traverses the screen space from the left to right, starting at
-    // the bottom left
-    for y in (0..height).rev() {
-        for x in 0..width {
-            let mut anti_aliased_colour = Vector4::new3(0.0, 0.0, 0.0);
-            for _ in 0..anti_alias_sample_size {
-                let u = (x as f32 + rng.next_f32()) / width as f32;
-                let v = (y as f32 + rng.next_f32()) / height as f32;
-
-                let ray = camera.get_ray(u, v);
-                anti_aliased_colour += colour(&ray, &renderable_list[..]);
-            }
-
-            let col = anti_aliased_colour / (anti_alias_sample_size as f32);
-
-            let r = col.x;
-            let g = col.y;
-            let b = col.z;
-            image_data.push(r);
-            image_data.push(g);
-            image_data.push(b);
-        }
-    }
-
-    image_data
+    let camera = Camera::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 0.0, -1.0),
+                    Vector4::new3(0.0, 1.0, 0.0), 90.0,
+                    width as f32 / height as f32, 0.0, 1.0);
+    let renderable_list: Vec<Box<dyn Renderable>> = vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, NormalMaterial)),
+        Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, NormalMaterial)),
+    ];
+
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pixel = anti_alias_sample_size;
+    renderer.gamma = 1.0;
+
+    renderer.render(&renderable_list[..])
 }
 
+// Shows the surface normal as a colour, each component mapped from -1.0 to
+// 1.0 onto 0.0 to 1.0. Nothing is scattered, so the renderer shows exactly
+// what is emitted.
+struct NormalMaterial;
 
-// Linearly blends white and blue depending on the y value of the target
-// screen coordinate
-fn colour(ray: &Ray, renderable_list: &[Box<Renderable>]) -> Vector4 {
-    if let Some(renderable_intersection) = renderable_list.intersects(ray, 0.0, std::f32::MAX) {
-        return (renderable_intersection.normal + 1.0) * 0.5;
+impl Material for NormalMaterial {
+    fn scatter(&self, _: &Ray, _: &IntersectionRecord, _: &mut dyn Rng) -> Option<(Ray, Vector4)> {
+        None
     }
 
-    // Make unit so (-1.0 < y < 1.0) holds true
-    let unit_direction = ray.direction().unit_vector();
-
-    // Scale to 0.0 < t < 1.0
-    let t = 0.5 * (unit_direction.y + 1.0);
-
-    // Linear interpolation (lerp) of white (1.0, 1.0, 1.0) and blue (0.5, 0.7, 1.0)
-    //   blended_value = (1 - t) * start_value + t * end_value
-    (Vector4::new3(1.0, 1.0, 1.0) * (1.0 - t)) + (Vector4::new3(0.5, 0.7, 1.0) * t)
+    fn emitted(&self, hit_record: &IntersectionRecord) -> Vector4 {
+        (hit_record.normal + 1.0) * 0.5
+    }
 }
-
@@ -0,0 +1,68 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::io;
+use ppm::PPMWriter;
+use vector::{ Matrix4, Vector4 };
+
+use weekendraytracer::*;
+
+// The Cornell box with its two blocks replaced by black and white smoke,
+// from chapter 8 of "Ray Tracing: The Next Week".
+fn main() {
+    let width  = 300;
+    let height = 300;
+
+    let image_data = generate_image_data(width, height);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let mut writer = PPMWriter::new(&mut out);
+    writer.write(&image_data[..], width, height).unwrap();
+}
+
+fn cornell_smoke() -> Vec<Box<dyn Renderable>> {
+    let red   = Lambertian::new(Vector4::new3(0.65, 0.05, 0.05));
+    let white = Lambertian::new(Vector4::new3(0.73, 0.73, 0.73));
+    let green = Lambertian::new(Vector4::new3(0.12, 0.45, 0.15));
+    let light = DiffuseLight::new(Vector4::new3(7.0, 7.0, 7.0));
+
+    let up = Vector4::new3(0.0, 1.0, 0.0);
+
+    vec![
+        Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green).flip_normal()),
+        Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)),
+        Box::new(XZRect::new(113.0, 443.0, 127.0, 432.0, 554.0, light).flip_normal()),
+        Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone()).flip_normal()),
+        Box::new(XYRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone()).flip_normal()),
+        Box::new(Plane::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 1.0, 0.0), white.clone())),
+        Box::new(ConstantMedium::new(Transformed::new(
+            AxisAlignedBox::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(165.0, 165.0, 165.0), white.clone()),
+            Matrix4::translation(Vector4::new3(130.0, 0.0, 65.0)) * Matrix4::rotation(up, -18.0_f32.to_radians())),
+            0.01, Vector4::new3(1.0, 1.0, 1.0))),
+        Box::new(ConstantMedium::new(Transformed::new(
+            AxisAlignedBox::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(165.0, 330.0, 165.0), white),
+            Matrix4::translation(Vector4::new3(265.0, 0.0, 295.0)) * Matrix4::rotation(up, 15.0_f32.to_radians())),
+            0.01, Vector4::new3(0.0, 0.0, 0.0))),
+    ]
+}
+
+fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
+    let look_from = Vector4::new3(278.0, 278.0, -800.0);
+    let look_at   = Vector4::new3(278.0, 278.0, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 40.0,
+                    width as f32 / height as f32, 0.0,
+                    10.0);
+
+    let scene = Bvh::new(cornell_smoke());
+
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pixel = 200;
+    renderer.background = Background::Solid(Vector4::new3(0.0, 0.0, 0.0));
+
+    renderer.render(&scene)
+}
@@ -0,0 +1,55 @@
+extern crate hdr;
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::env;
+use std::io;
+use hdr::HDRWriter;
+use ppm::PFMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// Writes the linear radiance of the chapter 11 scene, as a Radiance .hdr
+// image or, when run with `pfm` as the argument, as a portable float map.
+fn main() {
+    let width  = 200;
+    let height = 100;
+
+    let image_data = generate_image_data(width, height);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if env::args().nth(1).as_deref() == Some("pfm") {
+        PFMWriter::new(&mut out).write(&image_data[..], width, height).unwrap();
+    } else {
+        HDRWriter::new(&mut out).write(&image_data[..], width, height).unwrap();
+    }
+}
+
+fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
+    let look_from = Vector4::new3(3.0, 3.0, 2.0);
+    let look_at   = Vector4::new3(0.0, 0.0, -1.0);
+    let dist_to_focus = (look_from - look_at).length();
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 20.0,
+                    width as f32 / height as f32, 0.5,
+                    dist_to_focus);
+
+    let renderable_list: Vec<Box<dyn Renderable>> = vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, Lambertian::new(Vector4::new3(0.8, 0.3, 0.3)))),
+        Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, Lambertian::new(Vector4::new3(0.8, 0.8, 0.0)))),
+        Box::new(Sphere::new(Vector4::new3(1.0, 0.0, -1.0), 0.5, Metal::new(Vector4::new3(0.8, 0.6, 0.2), 0.3))),
+        Box::new(Sphere::new(Vector4::new3(-1.0, 0.0, -1.0), 0.5, Dielectric::new(1.5))),
+    ];
+
+    // Keep the linear values, tone mapping is left to whatever reads the
+    // image
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.gamma = 1.0;
+
+    renderer.render(&renderable_list[..])
+}
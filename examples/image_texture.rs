@@ -0,0 +1,47 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::env;
+use std::io;
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// Wraps an image around a sphere, e.g. an equirectangular map of the earth:
+//
+//   cargo run --example image_texture -- earth.ppm > earth_render.ppm
+fn main() {
+    let width  = 400;
+    let height = 200;
+
+    let path = env::args().nth(1).expect("usage: image_texture <image.ppm|image.pfm>");
+    let texture = ImageTexture::open(&path, TextureFilter::Bilinear, WrapMode::Repeat).unwrap();
+
+    let image_data = generate_image_data(width, height, texture);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let mut writer = PPMWriter::new(&mut out);
+    writer.write(&image_data[..], width, height).unwrap();
+}
+
+fn generate_image_data(width: usize, height: usize, texture: ImageTexture) -> Vec<f32> {
+    let look_from = Vector4::new3(13.0, 2.0, 3.0);
+    let look_at   = Vector4::new3(0.0, 0.0, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 20.0,
+                    width as f32 / height as f32, 0.0,
+                    (look_from - look_at).length());
+
+    let renderable_list: Vec<Box<dyn Renderable>> = vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, 0.0, 0.0), 2.0, Lambertian::textured(texture))),
+    ];
+
+    let renderer = Renderer::new(camera, width, height);
+
+    renderer.render(&renderable_list[..])
+}
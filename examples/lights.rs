@@ -0,0 +1,47 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::io;
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// A scene lit only by emissive spheres, with nothing but black behind it.
+fn main() {
+    let width  = 400;
+    let height = 200;
+
+    let image_data = generate_image_data(width, height);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let mut writer = PPMWriter::new(&mut out);
+    writer.write(&image_data[..], width, height).unwrap();
+}
+
+fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
+    let look_from = Vector4::new3(13.0, 2.0, 3.0);
+    let look_at   = Vector4::new3(0.0, 1.0, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 30.0,
+                    width as f32 / height as f32, 0.0,
+                    (look_from - look_at).length());
+
+    let renderable_list: Vec<Box<dyn Renderable>> = vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, -1000.0, 0.0), 1000.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))),
+        Box::new(Sphere::new(Vector4::new3(0.0, 1.0, 0.0), 1.0, Lambertian::new(Vector4::new3(0.4, 0.2, 0.1)))),
+        Box::new(Sphere::new(Vector4::new3(-3.0, 1.0, 2.0), 1.0, Metal::new(Vector4::new3(0.7, 0.6, 0.5), 0.0))),
+        Box::new(Sphere::new(Vector4::new3(0.0, 4.0, 0.0), 1.0, DiffuseLight::new(Vector4::new3(4.0, 4.0, 4.0)))),
+        Box::new(Sphere::new(Vector4::new3(3.0, 0.5, -2.0), 0.5, DiffuseLight::new(Vector4::new3(4.0, 1.0, 0.5)))),
+    ];
+
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pixel = 200;
+    renderer.background = Background::Solid(Vector4::new3(0.0, 0.0, 0.0));
+
+    renderer.render(&renderable_list[..])
+}
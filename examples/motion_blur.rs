@@ -0,0 +1,74 @@
+extern crate ppm;
+extern crate rand;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::io;
+use ppm::PPMWriter;
+use rand::Rng;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// The diffuse spheres of chapter 12 bouncing upwards while the shutter is
+// open, from chapter 1 of "Ray Tracing: The Next Week".
+fn main() {
+    let width  = 400;
+    let height = 200;
+
+    let image_data = generate_image_data(width, height);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let mut writer = PPMWriter::new(&mut out);
+    writer.write(&image_data[..], width, height).unwrap();
+}
+
+fn bouncing_scene() -> Vec<Box<dyn Renderable>> {
+    let mut renderable_list: Vec<Box<dyn Renderable>> = Vec::new();
+
+    // A fixed seed gives the same scene every run
+    let mut rng = random::seeded_rng(0);
+
+    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, -1000.0, 0.0), 1000.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Vector4::new3(
+                a as f32 + 0.9 * rng.next_f32(),
+                0.2,
+                b as f32 + 0.9 * rng.next_f32()
+            );
+
+            if (center - Vector4::new3(4.0, 0.2, 0.0)).length() > 0.9 {
+                let mat = Lambertian::new(Vector4::new3(rng.next_f32(), rng.next_f32(), rng.next_f32()));
+                let center1 = center + Vector4::new3(0.0, 0.5 * rng.next_f32(), 0.0);
+                renderable_list.push(Box::new(MovingSphere::new(center, center1, 0.0, 1.0, 0.2, mat)));
+            }
+        }
+    }
+
+    renderable_list.push(Box::new(Sphere::new(Vector4::new3(0.0, 1.0, 0.0), 1.0, Dielectric::new(1.5))));
+    renderable_list.push(Box::new(Sphere::new(Vector4::new3(-4.0, 1.0, 0.0), 1.0, Lambertian::new(Vector4::new3(0.4, 0.2, 0.1)))));
+    renderable_list.push(Box::new(Sphere::new(Vector4::new3(4.0, 1.0, 0.0), 1.0, Metal::new(Vector4::new3(0.7, 0.6, 0.5), 0.0))));
+
+    renderable_list
+}
+
+fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
+    let look_from = Vector4::new3(13.0, 2.0, 3.0);
+    let look_at   = Vector4::new3(0.0, 0.0, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 20.0,
+                    width as f32 / height as f32, 0.0,
+                    10.0)
+        .with_shutter(0.0, 1.0);
+
+    let scene = Bvh::new(bouncing_scene());
+
+    let renderer = Renderer::new(camera, width, height);
+
+    renderer.render(&scene)
+}
@@ -0,0 +1,89 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::env;
+use std::fs::File;
+use std::io::{ self, BufWriter };
+use std::time::Duration;
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// Renders the Cornell box progressively, rewriting a preview image after
+// every pass so it can be watched as it converges:
+//
+//   cargo run --release --example progressive -- preview.ppm
+//
+// Given a checkpoint file as well, the render is saved to it every thirty
+// seconds, and picks up from it again if stopped and rerun:
+//
+//   cargo run --release --example progressive -- preview.ppm render.ckpt
+fn main() {
+    let width  = 300;
+    let height = 300;
+
+    let preview_path = env::args().nth(1).expect("usage: progressive <preview.ppm> [checkpoint]");
+    let checkpoint_path = env::args().nth(2);
+
+    let renderer = renderer(width, height);
+    let scene = Bvh::new(cornell_box());
+
+    let preview = |framebuffer: &Framebuffer, progress| {
+        if let Progress::Pass { pass } = progress {
+            write_image(&preview_path, framebuffer, renderer.gamma).unwrap();
+            eprintln!("pass {} done, {} samples per pixel", pass + 1, framebuffer.sample_count(0, 0));
+        }
+    };
+
+    match checkpoint_path {
+        Some(checkpoint_path) => {
+            renderer.render_resumable(&scene, &checkpoint_path, Duration::from_secs(30), preview).unwrap();
+        }
+        None => {
+            let mut framebuffer = Framebuffer::new(width, height);
+            renderer.render_progressive(&scene, &mut framebuffer, preview);
+        }
+    }
+}
+
+fn write_image(path: &str, framebuffer: &Framebuffer, gamma: f32) -> io::Result<()> {
+    let image_data = framebuffer.image(gamma);
+    let mut out = BufWriter::new(File::create(path)?);
+    PPMWriter::new(&mut out).write(&image_data[..], framebuffer.width(), framebuffer.height())
+}
+
+fn cornell_box() -> Vec<Box<dyn Renderable>> {
+    let red   = Lambertian::new(Vector4::new3(0.65, 0.05, 0.05));
+    let white = Lambertian::new(Vector4::new3(0.73, 0.73, 0.73));
+    let green = Lambertian::new(Vector4::new3(0.12, 0.45, 0.15));
+    let light = DiffuseLight::new(Vector4::new3(15.0, 15.0, 15.0));
+
+    vec![
+        Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green).flip_normal()),
+        Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)),
+        Box::new(XZRect::new(213.0, 343.0, 227.0, 332.0, 554.0, light).flip_normal()),
+        Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone()).flip_normal()),
+        Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, white.clone())),
+        Box::new(XYRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white.clone()).flip_normal()),
+        Box::new(AxisAlignedBox::new(Vector4::new3(130.0, 0.0, 65.0), Vector4::new3(295.0, 165.0, 230.0), white.clone())),
+        Box::new(AxisAlignedBox::new(Vector4::new3(265.0, 0.0, 295.0), Vector4::new3(430.0, 330.0, 460.0), white)),
+    ]
+}
+
+fn renderer(width: usize, height: usize) -> Renderer {
+    let look_from = Vector4::new3(278.0, 278.0, -800.0);
+    let look_at   = Vector4::new3(278.0, 278.0, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 40.0,
+                    width as f32 / height as f32, 0.0,
+                    10.0);
+
+    let mut renderer = Renderer::new(camera, width, height);
+    renderer.samples_per_pixel = 200;
+    renderer.samples_per_pass = 10;
+    renderer.background = Background::Solid(Vector4::new3(0.0, 0.0, 0.0));
+    renderer
+}
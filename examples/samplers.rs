@@ -0,0 +1,72 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// Renders the same scene with each sampler at 16 samples per pixel, writing
+// the images to the given directory and printing how far each one is from a
+// render with many more samples:
+//
+//   cargo run --release --example samplers -- output_directory
+fn main() {
+    let width  = 200;
+    let height = 100;
+
+    let directory = env::args().nth(1).expect("usage: samplers <output directory>");
+    let scene = scene();
+
+    let mut reference = renderer(width, height);
+    reference.samples_per_pixel = 1024;
+    reference.seed = 1;
+    let reference = reference.render(&scene[..]);
+
+    let samplers: Vec<(&str, Box<dyn Sampler>)> = vec![
+        ("independent", Box::new(IndependentSampler)),
+        ("stratified", Box::new(StratifiedSampler::new(4))),
+        ("halton", Box::new(HaltonSampler)),
+        ("sobol", Box::new(SobolSampler)),
+    ];
+
+    for (name, sampler) in samplers {
+        let mut renderer = renderer(width, height);
+        renderer.samples_per_pixel = 16;
+        renderer.sampler = sampler;
+        let image = renderer.render(&scene[..]);
+
+        let squared_error: f32 = image.iter().zip(reference.iter()).map(|(a, b)| (a - b) * (a - b)).sum();
+        println!("{:>12}: RMS error {:.4}", name, (squared_error / image.len() as f32).sqrt());
+
+        let path = Path::new(&directory).join(format!("{}.ppm", name));
+        let mut file = BufWriter::new(File::create(path).unwrap());
+        PPMWriter::new(&mut file).write(&image[..], width, height).unwrap();
+    }
+}
+
+fn scene() -> Vec<Box<dyn Renderable>> {
+    vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, Lambertian::new(Vector4::new3(0.8, 0.8, 0.0)))),
+        Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, Lambertian::new(Vector4::new3(0.1, 0.2, 0.5)))),
+        Box::new(Sphere::new(Vector4::new3(1.0, 0.0, -1.0), 0.5, Metal::new(Vector4::new3(0.8, 0.6, 0.2), 0.3))),
+        Box::new(Sphere::new(Vector4::new3(-1.0, 0.0, -1.0), 0.5, Dielectric::new(1.5))),
+    ]
+}
+
+fn renderer(width: usize, height: usize) -> Renderer {
+    let look_from = Vector4::new3(3.0, 3.0, 2.0);
+    let look_at   = Vector4::new3(0.0, 0.0, -1.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 20.0,
+                    width as f32 / height as f32, 0.5,
+                    (look_from - look_at).length());
+
+    Renderer::new(camera, width, height)
+}
@@ -0,0 +1,52 @@
+extern crate ppm;
+extern crate vector;
+extern crate weekendraytracer;
+
+use std::io;
+use ppm::PPMWriter;
+use vector::Vector4;
+
+use weekendraytracer::*;
+
+// A checkered floor beneath spheres with procedural noise textures.
+fn main() {
+    let width  = 400;
+    let height = 200;
+
+    let image_data = generate_image_data(width, height);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let mut writer = PPMWriter::new(&mut out);
+    writer.write(&image_data[..], width, height).unwrap();
+}
+
+fn generate_image_data(width: usize, height: usize) -> Vec<f32> {
+    let look_from = Vector4::new3(13.0, 2.0, 3.0);
+    let look_at   = Vector4::new3(0.0, 1.0, 0.0);
+
+    let camera = Camera::new(look_from, look_at,
+                    Vector4::new3(0.0, 1.0, 0.0), 30.0,
+                    width as f32 / height as f32, 0.0,
+                    (look_from - look_at).length());
+
+    let checker = CheckerTexture::new(
+        ConstantTexture::new(Vector4::new3(0.2, 0.3, 0.1)),
+        ConstantTexture::new(Vector4::new3(0.9, 0.9, 0.9)),
+        10.0);
+
+    let renderable_list: Vec<Box<dyn Renderable>> = vec![
+        Box::new(Sphere::new(Vector4::new3(0.0, -1000.0, 0.0), 1000.0, Lambertian::textured(checker))),
+        Box::new(Sphere::new(Vector4::new3(0.0, 1.0, 0.0), 1.0,
+                             Lambertian::textured(NoiseTexture::new(NoiseKind::Marble, 4.0, 0)))),
+        Box::new(Sphere::new(Vector4::new3(-1.0, 1.0, 2.5), 1.0,
+                             Lambertian::textured(NoiseTexture::new(NoiseKind::Turbulence, 4.0, 0)))),
+        Box::new(Sphere::new(Vector4::new3(1.0, 1.0, -2.5), 1.0,
+                             Metal::textured(NoiseTexture::new(NoiseKind::Smooth, 4.0, 0), 0.1))),
+    ];
+
+    let renderer = Renderer::new(camera, width, height);
+
+    renderer.render(&renderable_list[..])
+}
@@ -0,0 +1,84 @@
+use ray::Ray;
+use vector::Vector4;
+
+/// An axis-aligned bounding box, the region between the `min` and `max`
+/// corners.
+#[derive(Copy, Clone)]
+pub struct Aabb {
+    pub min: Vector4,
+    pub max: Vector4,
+}
+
+impl Aabb {
+    pub fn new(min: Vector4, max: Vector4) -> Self {
+        Aabb {
+            min,
+            max,
+        }
+    }
+
+    /// Creates the smallest box containing both `a` and `b`.
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        Aabb::new(
+            Vector4::new3(a.min.x.min(b.min.x), a.min.y.min(b.min.y), a.min.z.min(b.min.z)),
+            Vector4::new3(a.max.x.max(b.max.x), a.max.y.max(b.max.y), a.max.z.max(b.max.z)),
+        )
+    }
+
+    /// Grows the box to contain `point`.
+    pub fn include(&self, point: Vector4) -> Self {
+        Aabb::surrounding(self, &Aabb::new(point, point))
+    }
+
+    pub fn centroid(&self) -> Vector4 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let extent = self.max - self.min;
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    /// The axis along which the box is longest, 0, 1 or 2 for x, y or z.
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Tests whether `ray` passes through the box anywhere between
+    /// `distance_min` and `distance_max`, using the slab method.
+    pub fn hit(&self, ray: &Ray, distance_min: f32, distance_max: f32) -> bool {
+        let origin = ray.origin();
+        let direction = ray.direction();
+
+        let mut distance_min = distance_min;
+        let mut distance_max = distance_max;
+
+        for axis in 0..3 {
+            let inverse_direction = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse_direction;
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse_direction;
+
+            if inverse_direction < 0.0 {
+                ::std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // Written so that a NaN from a ray lying in the slab plane
+            // leaves the interval alone.
+            distance_min = if t0 > distance_min { t0 } else { distance_min };
+            distance_max = if t1 < distance_max { t1 } else { distance_max };
+
+            if distance_max < distance_min {
+                return false;
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,55 @@
+use ppm::PFMWriter;
+use std::fs::File;
+use std::io::{ self, BufWriter };
+use std::path::Path;
+
+/// Arbitrary output variables, auxiliary images describing what the camera
+/// saw first in each pixel, for compositing and denoising.
+///
+/// Each pass is averaged over the samples of a pixel, and laid out one
+/// value or RGB triple per pixel starting at the top left like the image.
+pub struct Aovs {
+    pub width: usize,
+    pub height: usize,
+
+    /// The distance from the camera to the first hit, averaged over the
+    /// samples which hit something. Infinite where every sample escaped.
+    pub depth: Vec<f32>,
+
+    /// The world space normal at the first hit, facing the camera. Zero
+    /// where every sample escaped.
+    pub normal: Vec<f32>,
+
+    /// The albedo of the material at the first hit, or the background
+    /// colour where a sample escaped.
+    pub albedo: Vec<f32>,
+
+    /// The ID of the object hit by most of the samples, see
+    /// `IntersectionRecord::object_id`. 0 for the background.
+    pub object_id: Vec<u32>,
+}
+
+impl Aovs {
+    /// Writes each pass to its own portable float map in `directory`:
+    /// `depth.pfm`, `normal.pfm`, `albedo.pfm` and `object_id.pfm`. Object
+    /// IDs are stored as floats, which hold them exactly up to 2^24.
+    pub fn save<P: AsRef<Path>>(&self, directory: P) -> io::Result<()> {
+        let directory = directory.as_ref();
+        let object_id: Vec<f32> = self.object_id.iter().map(|&id| id as f32).collect();
+
+        write_pfm(&directory.join("depth.pfm"), &self.depth, self.width, self.height, 1)?;
+        write_pfm(&directory.join("normal.pfm"), &self.normal, self.width, self.height, 3)?;
+        write_pfm(&directory.join("albedo.pfm"), &self.albedo, self.width, self.height, 3)?;
+        write_pfm(&directory.join("object_id.pfm"), &object_id, self.width, self.height, 1)
+    }
+}
+
+fn write_pfm(path: &Path, data: &[f32], width: usize, height: usize, channels: usize) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    if channels == 1 {
+        PFMWriter::greyscale(&mut file).write(data, width, height)
+    } else {
+        PFMWriter::new(&mut file).write(data, width, height)
+    }
+}
@@ -0,0 +1,320 @@
+use aabb::Aabb;
+use rand::Rng;
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+
+/// The number of buckets centroids are binned into when looking for the
+/// cheapest split.
+const SAH_BUCKETS: usize = 12;
+
+/// Nodes with this many objects or fewer become leaves when splitting them
+/// isn't expected to pay off.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// The cost of testing a ray against a node's box, relative to testing it
+/// against one object.
+const TRAVERSAL_COST: f32 = 0.125;
+
+struct Bounded {
+    bounding_box: Aabb,
+    object_id: u32,
+    renderable: Box<dyn Renderable>,
+}
+
+/// Intersects a list of renderables tagged with the object IDs they were
+/// given when the hierarchy was built.
+fn intersects_identified(renderables: &[(u32, Box<dyn Renderable>)], ray: &Ray,
+                         distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+    let mut closest_so_far = distance_max;
+    let mut record = None;
+
+    for &(object_id, ref renderable) in renderables {
+        if let Some(mut intersection_record) = renderable.intersects(ray, distance_min, closest_so_far, rng) {
+            closest_so_far = intersection_record.distance;
+            intersection_record.object_id = object_id;
+            record = Some(intersection_record);
+        }
+    }
+
+    record
+}
+
+/// A node in a bounding volume hierarchy, a tree of boxes which lets a ray
+/// skip every object in a box it misses.
+///
+/// The tree is built using the surface area heuristic, each node is split
+/// where the expected cost of intersecting its children is lowest.
+pub enum BvhNode {
+    Leaf {
+        bounding_box: Aabb,
+        renderables: Vec<(u32, Box<dyn Renderable>)>,
+    },
+    Branch {
+        bounding_box: Aabb,
+        left: Box<BvhNode>,
+        right: Box<BvhNode>,
+    },
+}
+
+impl BvhNode {
+    fn build(mut objects: Vec<Bounded>) -> Self {
+        let bounding_box = objects.iter().skip(1)
+            .fold(objects[0].bounding_box, |surrounding, object| Aabb::surrounding(&surrounding, &object.bounding_box));
+
+        if objects.len() == 1 {
+            return BvhNode::leaf(bounding_box, objects);
+        }
+
+        let centroid_bounds = objects.iter().skip(1)
+            .fold(Aabb::new(objects[0].bounding_box.centroid(), objects[0].bounding_box.centroid()),
+                  |bounds, object| bounds.include(object.bounding_box.centroid()));
+
+        let axis = centroid_bounds.longest_axis();
+        let axis_min = centroid_bounds.min[axis];
+        let axis_extent = centroid_bounds.max[axis] - axis_min;
+
+        // Every centroid is in the same place, there's nothing to split on
+        if axis_extent <= 0.0 {
+            if objects.len() <= MAX_LEAF_SIZE {
+                return BvhNode::leaf(bounding_box, objects);
+            }
+
+            let right = objects.split_off(objects.len() / 2);
+            return BvhNode::branch(bounding_box, objects, right);
+        }
+
+        let bucket_of = |object: &Bounded| {
+            let offset = (object.bounding_box.centroid()[axis] - axis_min) / axis_extent;
+            ((offset * SAH_BUCKETS as f32) as usize).min(SAH_BUCKETS - 1)
+        };
+
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut boxes: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+        for object in objects.iter() {
+            let bucket = bucket_of(object);
+            counts[bucket] += 1;
+            boxes[bucket] = Some(match boxes[bucket] {
+                Some(bucket_box) => Aabb::surrounding(&bucket_box, &object.bounding_box),
+                None => object.bounding_box,
+            });
+        }
+
+        // Evaluate the cost of splitting after each bucket
+        let mut best_split = 0;
+        let mut best_cost = f32::MAX;
+        for split in 0..SAH_BUCKETS - 1 {
+            let (left_count, left_box) = merge_buckets(&counts[..split + 1], &boxes[..split + 1]);
+            let (right_count, right_box) = merge_buckets(&counts[split + 1..], &boxes[split + 1..]);
+
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+
+            let cost = TRAVERSAL_COST +
+                (left_count as f32 * left_box.unwrap().surface_area() +
+                 right_count as f32 * right_box.unwrap().surface_area()) / bounding_box.surface_area();
+
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = split;
+            }
+        }
+
+        let leaf_cost = objects.len() as f32;
+        if objects.len() <= MAX_LEAF_SIZE && leaf_cost <= best_cost {
+            return BvhNode::leaf(bounding_box, objects);
+        }
+
+        let (mut left, mut right): (Vec<Bounded>, Vec<Bounded>) =
+            objects.into_iter().partition(|object| bucket_of(object) <= best_split);
+
+        // Centroids which aren't finite all land in the first bucket, so
+        // the buckets may not split the objects at all
+        if left.is_empty() || right.is_empty() {
+            left.append(&mut right);
+            left.sort_by(|a, b| a.bounding_box.centroid()[axis].total_cmp(&b.bounding_box.centroid()[axis]));
+            right = left.split_off(left.len() / 2);
+        }
+
+        BvhNode::branch(bounding_box, left, right)
+    }
+
+    fn leaf(bounding_box: Aabb, objects: Vec<Bounded>) -> Self {
+        BvhNode::Leaf {
+            bounding_box,
+            renderables: objects.into_iter().map(|object| (object.object_id, object.renderable)).collect(),
+        }
+    }
+
+    fn branch(bounding_box: Aabb, left: Vec<Bounded>, right: Vec<Bounded>) -> Self {
+        BvhNode::Branch {
+            bounding_box,
+            left: Box::new(BvhNode::build(left)),
+            right: Box::new(BvhNode::build(right)),
+        }
+    }
+}
+
+fn merge_buckets(counts: &[usize], boxes: &[Option<Aabb>]) -> (usize, Option<Aabb>) {
+    let count = counts.iter().sum();
+    let merged = boxes.iter().fold(None, |merged, bucket_box| {
+        match (merged, *bucket_box) {
+            (Some(a), Some(b)) => Some(Aabb::surrounding(&a, &b)),
+            (a, b) => a.or(b),
+        }
+    });
+
+    (count, merged)
+}
+
+impl Renderable for BvhNode {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        match *self {
+            BvhNode::Leaf { ref bounding_box, ref renderables } => {
+                if !bounding_box.hit(ray, distance_min, distance_max) {
+                    return None;
+                }
+
+                intersects_identified(renderables, ray, distance_min, distance_max, rng)
+            }
+            BvhNode::Branch { ref bounding_box, ref left, ref right } => {
+                if !bounding_box.hit(ray, distance_min, distance_max) {
+                    return None;
+                }
+
+                let left_record = left.intersects(ray, distance_min, distance_max, rng);
+                let closest_so_far = left_record.as_ref().map_or(distance_max, |record| record.distance);
+                let right_record = right.intersects(ray, distance_min, closest_so_far, rng);
+
+                right_record.or(left_record)
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match *self {
+            BvhNode::Leaf { bounding_box, .. } | BvhNode::Branch { bounding_box, .. } => Some(bounding_box),
+        }
+    }
+}
+
+/// A bounding volume hierarchy over a list of renderables, a drop in
+/// replacement for a `[Box<Renderable>]` scene.
+///
+/// Renderables without a bounding box, such as infinite planes, can't be
+/// placed in the tree and are tested against every ray.
+///
+/// Hits are given the same object IDs as the list would give them.
+pub struct Bvh {
+    root: Option<BvhNode>,
+    unbounded: Vec<(u32, Box<dyn Renderable>)>,
+}
+
+impl Bvh {
+    pub fn new(renderables: Vec<Box<dyn Renderable>>) -> Self {
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+
+        for (index, renderable) in renderables.into_iter().enumerate() {
+            let object_id = index as u32 + 1;
+            match renderable.bounding_box() {
+                Some(bounding_box) => bounded.push(Bounded { bounding_box, object_id, renderable }),
+                None => unbounded.push((object_id, renderable)),
+            }
+        }
+
+        Bvh {
+            root: if bounded.is_empty() { None } else { Some(BvhNode::build(bounded)) },
+            unbounded,
+        }
+    }
+}
+
+impl Renderable for Bvh {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        let unbounded_record = intersects_identified(&self.unbounded, ray, distance_min, distance_max, rng);
+        let closest_so_far = unbounded_record.as_ref().map_or(distance_max, |record| record.distance);
+
+        let bounded_record = self.root.as_ref().and_then(|root| root.intersects(ray, distance_min, closest_so_far, rng));
+
+        bounded_record.or(unbounded_record)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+
+        self.root.as_ref().and_then(|root| root.bounding_box())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use random::seeded_rng;
+    use rand::Rng;
+    use sphere::Sphere;
+    use vector::Vector4;
+
+    fn random_spheres(count: usize) -> Vec<Box<dyn Renderable>> {
+        let mut rng = seeded_rng(3);
+        (0..count).map(|_| {
+            let center = Vector4::new3(rng.next_f32() * 20.0 - 10.0, rng.next_f32() * 20.0 - 10.0, rng.next_f32() * 20.0 - 10.0);
+            Box::new(Sphere::new(center, rng.next_f32() + 0.1, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))) as Box<dyn Renderable>
+        }).collect()
+    }
+
+    #[test]
+    fn test_bvh_matches_list() {
+        let list = random_spheres(200);
+        let bvh = Bvh::new(random_spheres(200));
+
+        let mut rng = seeded_rng(4);
+        for _ in 0..1000 {
+            let origin = Vector4::new3(rng.next_f32() * 30.0 - 15.0, rng.next_f32() * 30.0 - 15.0, rng.next_f32() * 30.0 - 15.0);
+            let direction = Vector4::new3(rng.next_f32() - 0.5, rng.next_f32() - 0.5, rng.next_f32() - 0.5);
+            let ray = Ray::new(origin, direction);
+
+            // Both the hit and which object it was should agree
+            let expected = list[..].intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).map(|record| (record.distance, record.object_id));
+            let actual = bvh.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).map(|record| (record.distance, record.object_id));
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_bvh_with_non_finite_objects() {
+        let material = Lambertian::new(Vector4::new3(0.5, 0.5, 0.5));
+        let mut spheres = random_spheres(20);
+        for &center in &[f32::INFINITY, f32::NEG_INFINITY, f32::NAN] {
+            for _ in 0..5 {
+                spheres.push(Box::new(Sphere::new(Vector4::new3(center, 0.0, 0.0), 1.0, material.clone())));
+            }
+        }
+
+        let list = random_spheres(20);
+        let bvh = Bvh::new(spheres);
+
+        // The finite spheres can still be hit
+        let ray = Ray::new(Vector4::new3(-15.0, 0.0, 0.0), Vector4::new3(1.0, 0.0, 0.0));
+        let expected = list[..].intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).map(|record| record.distance);
+        let actual = bvh.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).map(|record| record.distance);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_bvh_bounding_box() {
+        let bvh = Bvh::new(vec![
+            Box::new(Sphere::new(Vector4::new3(-1.0, 0.0, 0.0), 1.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))),
+            Box::new(Sphere::new(Vector4::new3(3.0, 1.0, 0.0), 0.5, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))),
+        ]);
+
+        let bounding_box = bvh.bounding_box().unwrap();
+        assert_eq!(bounding_box.min.x, -2.0);
+        assert_eq!(bounding_box.min.y, -1.0);
+        assert_eq!(bounding_box.max.x, 3.5);
+        assert_eq!(bounding_box.max.y, 1.5);
+    }
+}
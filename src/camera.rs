@@ -1,17 +1,8 @@
 use vector::Vector4;
 use ray::Ray;
+use rand::Rng;
+use random::random_in_unit_disk;
 use std::f32::consts::PI;
-use rand;
-
-fn random_in_unit_disk() -> Vector4 {
-    loop {
-        let p =
-            Vector4::new3(rand::random::<f32>(), rand::random::<f32>(), 0.0)  * 2.0 - Vector4::new3(1.0, 1.0, 0.0);
-        if p.dot3(p) < 1.0 {
-            return p;
-        }
-    }
-}
 
 pub struct Camera {
     origin: Vector4,
@@ -22,6 +13,9 @@ pub struct Camera {
     u: Vector4,
     v: Vector4,
     lens_radius: f32,
+
+    shutter_open: f32,
+    shutter_close: f32,
 }
 
 impl Camera {
@@ -45,13 +39,36 @@ impl Camera {
             u: u,
             v: v,
             lens_radius: lens_radius,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let rd = random_in_unit_disk() * self.lens_radius;
+    /// Keeps the shutter open from `open` until `close`, each ray is cast at
+    /// a random time in between so moving objects blur.
+    pub fn with_shutter(mut self, open: f32, close: f32) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close;
+        self
+    }
+
+    /// Casts a ray through `(u, v)` on the screen, drawing the lens position
+    /// from `rng` and then the time.
+    pub fn get_ray(&self, u: f32, v: f32, rng: &mut dyn Rng) -> Ray {
+        let lens = random_in_unit_disk(rng);
+        let time = rng.next_f32();
+        self.ray_through(u, v, lens, time)
+    }
+
+    /// Casts a ray through `(u, v)` on the screen from `lens`, a point in the
+    /// unit disk, at `time` from 0.0 when the shutter opens to 1.0 when it
+    /// closes.
+    pub fn ray_through(&self, u: f32, v: f32, lens: Vector4, time: f32) -> Ray {
+        let rd = lens * self.lens_radius;
         let offset = self.u * rd.x + self.v * rd.y;
-        Ray::new(self.origin + offset,
-                 self.lower_left + (self.horizontal * u) + (self.vertical * v) - self.origin - offset)
+        let time = self.shutter_open + time * (self.shutter_close - self.shutter_open);
+        Ray::with_time(self.origin + offset,
+                       self.lower_left + (self.horizontal * u) + (self.vertical * v) - self.origin - offset,
+                       time)
     }
 }
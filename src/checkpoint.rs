@@ -0,0 +1,235 @@
+use framebuffer::Framebuffer;
+use std::fmt;
+use std::fs::{ self, File };
+use std::io::{ self, BufReader, BufWriter, Read, Write };
+use std::path::Path;
+use vector::Vector4;
+
+const MAGIC: &[u8; 8] = b"WRTCKPT\0";
+// Bumped whenever the layout below changes, so older checkpoints are
+// rejected rather than misread. The header is the magic and version, then
+// the width, height, seed, maximum depth, samples per pass and length of
+// the sampler's name, followed by the name and `PIXEL_BYTES` per pixel.
+const VERSION: u32 = 1;
+
+// Each pixel's three sums, sum of squares and sample count
+const PIXEL_BYTES: usize = 5 * 4;
+
+// Far longer than any sampler's name, so a corrupt length can't allocate
+// much before it is rejected
+const MAX_NAME_BYTES: usize = 256;
+
+/// A render saved part way through, everything needed to carry on adding
+/// samples exactly where it left off.
+///
+/// The random numbers for each sample are derived from the seed, the pixel
+/// and how many samples came before, so the seed and the per-pixel sample
+/// counts are the whole of the generator state.
+pub struct Checkpoint {
+    pub settings: CheckpointSettings,
+    pub framebuffer: Framebuffer,
+}
+
+/// The renderer settings a checkpoint was made with, which must be the same
+/// to resume it and get the image an uninterrupted render would.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointSettings {
+    pub seed: u32,
+
+    /// The `Sampler::name` of the sampler.
+    pub sampler: String,
+    pub max_depth: u32,
+    pub samples_per_pass: u32,
+}
+
+impl fmt::Display for CheckpointSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "seed {}, the {} sampler, max depth {} and {} samples per pass",
+               self.seed, self.sampler, self.max_depth, self.samples_per_pass)
+    }
+}
+
+/// Writes a checkpoint to `path`. The file is written alongside and then
+/// moved into place, so a render stopped part way through saving leaves
+/// the previous checkpoint intact.
+pub fn save_checkpoint<P: AsRef<Path>>(path: P, framebuffer: &Framebuffer, settings: &CheckpointSettings) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    {
+        let mut file = BufWriter::new(File::create(&temporary)?);
+        write_checkpoint(&mut file, framebuffer, settings)?;
+        file.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+    }
+
+    fs::rename(&temporary, path)
+}
+
+pub fn load_checkpoint<P: AsRef<Path>>(path: P) -> io::Result<Checkpoint> {
+    read_checkpoint(&mut BufReader::new(File::open(path)?))
+}
+
+/// Writes the sums and sample count of every pixel, little endian, with
+/// the sums stored bit for bit so a resumed render is exactly the same.
+pub fn write_checkpoint<TWrite: Write>(writer: &mut TWrite, framebuffer: &Framebuffer, settings: &CheckpointSettings) -> io::Result<()> {
+    if settings.sampler.len() > MAX_NAME_BYTES {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the sampler's name is too long"));
+    }
+
+    writer.write_all(MAGIC)?;
+    for value in &[VERSION, framebuffer.width() as u32, framebuffer.height() as u32, settings.seed,
+                   settings.max_depth, settings.samples_per_pass, settings.sampler.len() as u32] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.write_all(settings.sampler.as_bytes())?;
+
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            let sum = framebuffer.sum(x, y);
+            for value in &[sum.x, sum.y, sum.z, framebuffer.sum_of_squares(x, y)] {
+                writer.write_all(&value.to_bits().to_le_bytes())?;
+            }
+            writer.write_all(&framebuffer.sample_count(x, y).to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn read_checkpoint<TRead: Read>(reader: &mut TRead) -> io::Result<Checkpoint> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a render checkpoint".to_string()));
+    }
+
+    let version = read_u32(reader)?;
+    if version != VERSION {
+        return Err(invalid_data(format!("unsupported checkpoint version {}", version)));
+    }
+
+    let width = read_u32(reader)? as usize;
+    let height = read_u32(reader)? as usize;
+    let seed = read_u32(reader)?;
+    let max_depth = read_u32(reader)?;
+    let samples_per_pass = read_u32(reader)?;
+
+    let name_bytes = read_u32(reader)? as usize;
+    if name_bytes > MAX_NAME_BYTES {
+        return Err(invalid_data(format!("a sampler name of {} bytes is too long", name_bytes)));
+    }
+    let mut name = vec![0; name_bytes];
+    reader.read_exact(&mut name)?;
+    let sampler = String::from_utf8(name).map_err(|_| invalid_data("the sampler name isn't UTF-8".to_string()))?;
+
+    // The pixels are read before the framebuffer is made, so a size in a
+    // corrupt header can't allocate more than the file holds
+    let pixel_bytes = width.checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(PIXEL_BYTES))
+        .ok_or_else(|| invalid_data(format!("a {}x{} checkpoint is too large", width, height)))?;
+    let mut pixels = Vec::new();
+    reader.take(pixel_bytes as u64).read_to_end(&mut pixels)?;
+    if pixels.len() != pixel_bytes {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the checkpoint ends part way through the pixels"));
+    }
+
+    let mut framebuffer = Framebuffer::new(width, height);
+    let mut pixels = &pixels[..];
+    for y in 0..height {
+        for x in 0..width {
+            let sum = Vector4::new3(read_f32(&mut pixels)?, read_f32(&mut pixels)?, read_f32(&mut pixels)?);
+            let sum_of_squares = read_f32(&mut pixels)?;
+            let count = read_u32(&mut pixels)?;
+            framebuffer.set(x, y, sum, sum_of_squares, count);
+        }
+    }
+
+    Ok(Checkpoint {
+        settings: CheckpointSettings {
+            seed,
+            sampler,
+            max_depth,
+            samples_per_pass,
+        },
+        framebuffer,
+    })
+}
+
+fn read_u32<TRead: Read>(reader: &mut TRead) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32<TRead: Read>(reader: &mut TRead) -> io::Result<f32> {
+    read_u32(reader).map(f32::from_bits)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings() -> CheckpointSettings {
+        CheckpointSettings {
+            seed: 42,
+            sampler: "stratified 4".to_string(),
+            max_depth: 50,
+            samples_per_pass: 4,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.set(2, 1, Vector4::new3(0.1, 1e-30, 12345.678), 0.3, 7);
+
+        let mut bytes = Vec::new();
+        write_checkpoint(&mut bytes, &framebuffer, &settings()).unwrap();
+
+        let checkpoint = read_checkpoint(&mut &bytes[..]).unwrap();
+        assert_eq!(checkpoint.settings, settings());
+        assert_eq!(checkpoint.framebuffer.width(), 3);
+        assert_eq!(checkpoint.framebuffer.height(), 2);
+        assert_eq!(checkpoint.framebuffer.sample_count(2, 1), 7);
+        assert_eq!(checkpoint.framebuffer.sum(2, 1).y.to_bits(), 1e-30_f32.to_bits());
+        assert_eq!(checkpoint.framebuffer.sum(2, 1).z, 12345.678);
+        assert_eq!(checkpoint.framebuffer.sum_of_squares(2, 1), 0.3);
+        assert_eq!(checkpoint.framebuffer.sample_count(0, 0), 0);
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        assert!(read_checkpoint(&mut &b"P3\n1 1\n255\n0 0 0\n"[..]).is_err());
+
+        // Cut off part way through the pixels
+        let mut bytes = Vec::new();
+        write_checkpoint(&mut bytes, &Framebuffer::new(2, 2), &settings()).unwrap();
+        bytes.truncate(bytes.len() - 1);
+        assert!(read_checkpoint(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_oversized_headers() {
+        let mut bytes = Vec::new();
+        write_checkpoint(&mut bytes, &Framebuffer::new(1, 1), &settings()).unwrap();
+
+        // Sizes which overflow, and one which fits but is far larger than
+        // the file, are errors rather than huge allocations
+        for &(width, height) in &[(u32::MAX, u32::MAX), (100_000, 100_000)] {
+            let mut header = bytes.clone();
+            header[12..16].copy_from_slice(&width.to_le_bytes());
+            header[16..20].copy_from_slice(&height.to_le_bytes());
+            assert!(read_checkpoint(&mut &header[..]).is_err());
+        }
+
+        // So is a sampler name longer than any real one
+        let mut header = bytes.clone();
+        header[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_checkpoint(&mut &header[..]).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use renderer::gamma_correct;
+use vector::Vector4;
+
+/// A rectangle of pixels, in image coordinates counting from the top left.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The perceived brightness of a linear colour, using the Rec. 709 weights.
+pub fn luminance(colour: Vector4) -> f32 {
+    0.2126 * colour.x + 0.7152 * colour.y + 0.0722 * colour.z
+}
+
+/// An image being built up a few samples at a time, holding the sum of the
+/// samples taken in each pixel and how many there were.
+///
+/// The sum of the squared luminance of the samples is kept too, so the
+/// noise left in each pixel can be estimated.
+///
+/// Pixels are addressed in image coordinates, counting from the top left.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    sums: Vec<Vector4>,
+    sums_of_squares: Vec<f32>,
+    counts: Vec<u32>,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer with no samples in any pixel.
+    pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            sums: vec![Vector4::new3(0.0, 0.0, 0.0); width * height],
+            sums_of_squares: vec![0.0; width * height],
+            counts: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The sum of every sample taken in a pixel.
+    pub fn sum(&self, x: usize, y: usize) -> Vector4 {
+        self.sums[self.index(x, y)]
+    }
+
+    /// The sum of the squared luminance of every sample taken in a pixel.
+    pub fn sum_of_squares(&self, x: usize, y: usize) -> f32 {
+        self.sums_of_squares[self.index(x, y)]
+    }
+
+    pub fn sample_count(&self, x: usize, y: usize) -> u32 {
+        self.counts[self.index(x, y)]
+    }
+
+    /// The sample variance of the luminance of a pixel's samples, or
+    /// infinite if there are fewer than two.
+    pub fn variance(&self, x: usize, y: usize) -> f32 {
+        let index = self.index(x, y);
+        let count = self.counts[index] as f32;
+        if count < 2.0 {
+            return f32::INFINITY;
+        }
+
+        let sum = luminance(self.sums[index]);
+
+        // Rounding can take this a hair below zero for flat pixels
+        ((self.sums_of_squares[index] - sum * sum / count) / (count - 1.0)).max(0.0)
+    }
+
+    /// The average of the samples taken in a pixel, black if there are none
+    /// yet.
+    pub fn mean(&self, x: usize, y: usize) -> Vector4 {
+        let index = self.index(x, y);
+        match self.counts[index] {
+            0 => Vector4::new3(0.0, 0.0, 0.0),
+            count => self.sums[index] / count as f32,
+        }
+    }
+
+    /// The image so far as RGB triples starting at the top left, each pixel
+    /// the average of its samples raised to `1 / gamma`.
+    pub fn image(&self, gamma: f32) -> Vec<f32> {
+        let mut image = Vec::with_capacity(self.width * self.height * 3);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let colour = gamma_correct(self.mean(x, y), gamma);
+                image.extend_from_slice(&[colour.x, colour.y, colour.z]);
+            }
+        }
+        image
+    }
+
+    /// A false colour image of how many samples each pixel has, running
+    /// from black for none through blue, red and yellow to white for
+    /// `max_samples` or more.
+    pub fn sample_heatmap(&self, max_samples: u32) -> Vec<f32> {
+        let stops = [
+            Vector4::new3(0.0, 0.0, 0.0),
+            Vector4::new3(0.0, 0.0, 1.0),
+            Vector4::new3(1.0, 0.0, 0.0),
+            Vector4::new3(1.0, 1.0, 0.0),
+            Vector4::new3(1.0, 1.0, 1.0),
+        ];
+
+        self.counts.iter().flat_map(|&count| {
+            let t = (count as f32 / max_samples.max(1) as f32).min(1.0) * (stops.len() - 1) as f32;
+            let stop = (t as usize).min(stops.len() - 2);
+            let fraction = t - stop as f32;
+            let colour = stops[stop] * (1.0 - fraction) + stops[stop + 1] * fraction;
+            [colour.x, colour.y, colour.z]
+        }).collect()
+    }
+
+    /// Splits the image into tiles of `tile_size` pixels square, left to
+    /// right and top to bottom. Tiles on the right and bottom edges may be
+    /// smaller.
+    pub fn tiles(&self, tile_size: usize) -> Vec<Tile> {
+        let tile_size = tile_size.max(1);
+        let mut tiles = Vec::new();
+
+        for y in (0..self.height).step_by(tile_size) {
+            for x in (0..self.width).step_by(tile_size) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: tile_size.min(self.width - x),
+                    height: tile_size.min(self.height - y),
+                });
+            }
+        }
+
+        tiles
+    }
+
+    /// Replaces a pixel's accumulated samples.
+    pub(crate) fn set(&mut self, x: usize, y: usize, sum: Vector4, sum_of_squares: f32, count: u32) {
+        let index = self.index(x, y);
+        self.sums[index] = sum;
+        self.sums_of_squares[index] = sum_of_squares;
+        self.counts[index] = count;
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height, "pixel ({}, {}) is outside the framebuffer", x, y);
+        y * self.width + x
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_the_image() {
+        let framebuffer = Framebuffer::new(10, 7);
+        let tiles = framebuffer.tiles(4);
+
+        assert_eq!(tiles.len(), 3 * 2);
+        assert_eq!(tiles[2], Tile { x: 8, y: 0, width: 2, height: 4 });
+        assert_eq!(tiles[5], Tile { x: 8, y: 4, width: 2, height: 3 });
+
+        let covered: usize = tiles.iter().map(|tile| tile.width * tile.height).sum();
+        assert_eq!(covered, 10 * 7);
+    }
+
+    #[test]
+    fn test_mean() {
+        let mut framebuffer = Framebuffer::new(2, 2);
+        framebuffer.set(1, 0, Vector4::new3(3.0, 6.0, 9.0), 0.0, 3);
+
+        assert_eq!(framebuffer.mean(1, 0).y, 2.0);
+        assert_eq!(framebuffer.mean(0, 0).y, 0.0);
+
+        // Image data starts at the top left
+        let image = framebuffer.image(1.0);
+        assert_eq!(&image[3..6], &[1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_variance() {
+        // Two grey samples of brightness 1 and 3
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.set(0, 0, Vector4::new3(4.0, 4.0, 4.0), 1.0 + 9.0, 2);
+        assert!((framebuffer.variance(0, 0) - 2.0).abs() < 1e-5);
+
+        framebuffer.set(1, 0, Vector4::new3(1.0, 1.0, 1.0), 1.0, 1);
+        assert!(framebuffer.variance(1, 0).is_infinite());
+    }
+
+    #[test]
+    fn test_sample_heatmap() {
+        let mut framebuffer = Framebuffer::new(3, 1);
+        framebuffer.set(1, 0, Vector4::new3(0.0, 0.0, 0.0), 0.0, 4);
+        framebuffer.set(2, 0, Vector4::new3(0.0, 0.0, 0.0), 0.0, 64);
+
+        let heatmap = framebuffer.sample_heatmap(16);
+        assert_eq!(&heatmap[0..3], &[0.0, 0.0, 0.0]);
+        assert_eq!(&heatmap[3..6], &[0.0, 0.0, 1.0]);
+        assert_eq!(&heatmap[6..9], &[1.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,237 @@
+use ppm::{ Image, PPMReader };
+use std::fs::File;
+use std::io::{ self, BufReader };
+use std::path::Path;
+use texture::Texture;
+use vector::Vector4;
+
+/// How texels are combined when a lookup falls between them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// The single closest texel
+    Nearest,
+
+    /// A linear blend of the closest 2x2 texels
+    Bilinear,
+
+    /// A Catmull-Rom cubic blend of the closest 4x4 texels, sharper than
+    /// bilinear filtering when the image is magnified
+    Bicubic,
+}
+
+/// How surface coordinates outside of 0.0 to 1.0 map onto the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    /// The image tiles
+    Repeat,
+
+    /// The texels at the edges stretch out forever
+    Clamp,
+
+    /// The image tiles, every other copy flipped so the edges meet
+    Mirror,
+}
+
+/// A texture looked up from an image by surface coordinates. `u` runs left
+/// to right across the image and `v` runs bottom to top.
+pub struct ImageTexture {
+    image: Image,
+    filter: TextureFilter,
+    wrap: WrapMode,
+}
+
+impl ImageTexture {
+    /// Panics if the image is empty.
+    pub fn new(image: Image, filter: TextureFilter, wrap: WrapMode) -> Self {
+        assert!(image.width > 0 && image.height > 0 && image.channels > 0, "texture image is empty");
+
+        ImageTexture {
+            image,
+            filter,
+            wrap,
+        }
+    }
+
+    /// Loads any image the ppm crate can read, including float maps.
+    pub fn open<P: AsRef<Path>>(path: P, filter: TextureFilter, wrap: WrapMode) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let image = PPMReader::new(&mut file).read()?;
+
+        if image.width == 0 || image.height == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "texture image is empty"));
+        }
+
+        Ok(ImageTexture::new(image, filter, wrap))
+    }
+
+    /// The colour of the texel at `(x, y)`, counting from the top left,
+    /// after wrapping. Greyscale images are expanded to grey colours.
+    fn texel(&self, x: i64, y: i64) -> Vector4 {
+        let x = wrap(x, self.image.width, self.wrap);
+        let y = wrap(y, self.image.height, self.wrap);
+        let pixel = self.image.pixel(x, y);
+
+        if pixel.len() >= 3 {
+            Vector4::new3(pixel[0], pixel[1], pixel[2])
+        } else {
+            Vector4::new3(pixel[0], pixel[0], pixel[0])
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _: Vector4) -> Vector4 {
+        // Continuous texel coordinates, texel centres lie on half integers
+        let x = reduce(u * self.image.width as f32, self.image.width, self.wrap);
+        let y = reduce((1.0 - v) * self.image.height as f32, self.image.height, self.wrap);
+
+        match self.filter {
+            TextureFilter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            TextureFilter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = self.texel(x0, y0) * (1.0 - tx) + self.texel(x0 + 1, y0) * tx;
+                let bottom = self.texel(x0, y0 + 1) * (1.0 - tx) + self.texel(x0 + 1, y0 + 1) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+            TextureFilter::Bicubic => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let x_weights = catmull_rom_weights(x - x0);
+                let y_weights = catmull_rom_weights(y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let mut colour = Vector4::new3(0.0, 0.0, 0.0);
+                for (j, y_weight) in y_weights.iter().enumerate() {
+                    for (i, x_weight) in x_weights.iter().enumerate() {
+                        colour += self.texel(x0 + i as i64 - 1, y0 + j as i64 - 1) * (x_weight * y_weight);
+                    }
+                }
+
+                colour
+            }
+        }
+    }
+}
+
+/// The weights of the four texels around a lookup `t` of the way between
+/// the second and third.
+fn catmull_rom_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+/// Moves a continuous texel coordinate into a small range around the image
+/// without changing the texels it wraps to, so the filters' integer texel
+/// offsets can't overflow however far outside the image it was.
+fn reduce(coordinate: f32, size: usize, mode: WrapMode) -> f32 {
+    let size = size as f32;
+
+    let reduced = match mode {
+        WrapMode::Repeat => coordinate.rem_euclid(size),
+        WrapMode::Mirror => coordinate.rem_euclid(2.0 * size),
+
+        // Far enough out that even the bicubic filter only reads the edge
+        WrapMode::Clamp => coordinate.clamp(-2.0, size + 2.0),
+    };
+
+    // NaNs, and infinities which can't be tiled, land on the first texel
+    if reduced.is_finite() { reduced } else { 0.0 }
+}
+
+fn wrap(index: i64, size: usize, mode: WrapMode) -> usize {
+    let size = size as i64;
+
+    let wrapped = match mode {
+        WrapMode::Repeat => index.rem_euclid(size),
+        WrapMode::Clamp => index.clamp(0, size - 1),
+        WrapMode::Mirror => {
+            let period = index.rem_euclid(2 * size);
+            if period < size { period } else { 2 * size - 1 - period }
+        }
+    };
+
+    wrapped as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A 2x2 greyscale image, black on the left and white on the right
+    fn test_texture(filter: TextureFilter, wrap: WrapMode) -> ImageTexture {
+        let image = Image { width: 2, height: 2, channels: 1, data: vec![0.0, 1.0, 0.0, 1.0] };
+        ImageTexture::new(image, filter, wrap)
+    }
+
+    fn sample(texture: &ImageTexture, u: f32, v: f32) -> f32 {
+        texture.value(u, v, Vector4::new3(0.0, 0.0, 0.0)).x
+    }
+
+    #[test]
+    fn test_nearest() {
+        let texture = test_texture(TextureFilter::Nearest, WrapMode::Repeat);
+        assert_eq!(sample(&texture, 0.25, 0.5), 0.0);
+        assert_eq!(sample(&texture, 0.75, 0.5), 1.0);
+        assert_eq!(sample(&texture, 1.25, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_bilinear() {
+        let texture = test_texture(TextureFilter::Bilinear, WrapMode::Clamp);
+        assert_eq!(sample(&texture, 0.25, 0.5), 0.0);
+        assert_eq!(sample(&texture, 0.5, 0.5), 0.5);
+        assert_eq!(sample(&texture, 0.75, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_bicubic_interpolates_texel_centres() {
+        let texture = test_texture(TextureFilter::Bicubic, WrapMode::Clamp);
+        assert!(sample(&texture, 0.25, 0.5).abs() < 1e-6);
+        assert!((sample(&texture, 0.75, 0.5) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_coordinates_far_outside_the_image() {
+        let filters = [TextureFilter::Nearest, TextureFilter::Bilinear, TextureFilter::Bicubic];
+        let wraps = [WrapMode::Repeat, WrapMode::Clamp, WrapMode::Mirror];
+
+        for &filter in &filters {
+            for &wrap in &wraps {
+                let texture = test_texture(filter, wrap);
+                for &u in &[1e30, -1e30, f32::MAX, f32::INFINITY, f32::NEG_INFINITY, f32::NAN] {
+                    let value = sample(&texture, u, 0.5);
+                    assert!(value.is_finite(), "{:?} {:?} u = {}", filter, wrap, u);
+                    assert!(sample(&texture, 0.5, u).is_finite());
+                }
+            }
+        }
+
+        // Whole tiles away still lands on the same texels
+        let texture = test_texture(TextureFilter::Bilinear, WrapMode::Repeat);
+        assert_eq!(sample(&texture, 1000.625, 0.5), sample(&texture, 0.625, 0.5));
+        let texture = test_texture(TextureFilter::Nearest, WrapMode::Clamp);
+        assert_eq!(sample(&texture, 1e30, 0.5), 1.0);
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        assert_eq!(wrap(-1, 4, WrapMode::Repeat), 3);
+        assert_eq!(wrap(5, 4, WrapMode::Repeat), 1);
+        assert_eq!(wrap(-1, 4, WrapMode::Clamp), 0);
+        assert_eq!(wrap(9, 4, WrapMode::Clamp), 3);
+        assert_eq!(wrap(-1, 4, WrapMode::Mirror), 0);
+        assert_eq!(wrap(4, 4, WrapMode::Mirror), 3);
+        assert_eq!(wrap(9, 4, WrapMode::Mirror), 1);
+    }
+}
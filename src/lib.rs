@@ -1,15 +1,52 @@
+// The constructors written before field init shorthand name each field
+// explicitly, and are kept that way
+#![allow(clippy::redundant_field_names)]
+
 extern crate ppm;
 extern crate rand;
 extern crate vector;
 
+pub mod aabb;
+pub mod aov;
+pub mod bvh;
 pub mod camera;
+pub mod checkpoint;
+pub mod framebuffer;
+pub mod image_texture;
 pub mod material;
+pub mod medium;
+pub mod obj;
+pub mod parallel;
+pub mod plane;
+pub mod random;
 pub mod ray;
+pub mod rect;
 pub mod renderable;
+pub mod renderer;
+pub mod sampler;
 pub mod sphere;
+pub mod texture;
+pub mod transform;
+pub mod triangle;
 
+pub use aabb::*;
+pub use aov::*;
+pub use bvh::*;
 pub use camera::*;
+pub use checkpoint::*;
+pub use framebuffer::*;
+pub use image_texture::*;
 pub use material::*;
+pub use medium::*;
+pub use obj::*;
+pub use parallel::*;
+pub use plane::*;
 pub use ray::*;
+pub use rect::*;
 pub use renderable::*;
+pub use renderer::*;
+pub use sampler::*;
 pub use sphere::*;
+pub use texture::*;
+pub use transform::*;
+pub use triangle::*;
@@ -1,59 +1,91 @@
 use ray::Ray;
-use rand;
+use rand::Rng;
+use random::random_in_unit_sphere;
+use std::sync::Arc;
+use texture::{ ConstantTexture, Texture };
 use vector::Vector4;
 
 use renderable::IntersectionRecord;
 
-pub trait Material {
-    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord) -> Option<(Ray, Vector4)>;
+pub trait Material: Send + Sync {
+    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord, rng: &mut dyn Rng) -> Option<(Ray, Vector4)>;
+
+    /// The light given off at the hit point, materials which aren't light
+    /// sources emit nothing.
+    fn emitted(&self, _hit_record: &IntersectionRecord) -> Vector4 {
+        Vector4::new3(0.0, 0.0, 0.0)
+    }
+
+    /// The fraction of light reflected at the hit point regardless of the
+    /// lighting, for the albedo pass. Materials which reflect nothing are
+    /// black.
+    fn albedo(&self, _hit_record: &IntersectionRecord) -> Vector4 {
+        Vector4::new3(0.0, 0.0, 0.0)
+    }
 }
 
 #[derive(Clone)]
 pub struct Lambertian {
-    albedo: Vector4,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Vector4) -> Self {
+        Lambertian::textured(ConstantTexture::new(albedo))
+    }
+
+    pub fn textured<TTexture: Texture + 'static>(albedo: TTexture) -> Self {
         Lambertian {
-            albedo: albedo,
+            albedo: Arc::new(albedo),
         }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _: &Ray, hit_record: &IntersectionRecord) -> Option<(Ray, Vector4)> {
-        let target = hit_record.intersection_point + hit_record.normal + random_in_unit_sphere();
-        Some((Ray::new(hit_record.intersection_point, target - hit_record.intersection_point), self.albedo))
+    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord, rng: &mut dyn Rng) -> Option<(Ray, Vector4)> {
+        let target = hit_record.intersection_point + hit_record.normal + random_in_unit_sphere(rng);
+        Some((Ray::with_time(hit_record.intersection_point, target - hit_record.intersection_point, ray_in.time()), self.albedo(hit_record)))
+    }
+
+    fn albedo(&self, hit_record: &IntersectionRecord) -> Vector4 {
+        self.albedo.value(hit_record.u, hit_record.v, hit_record.intersection_point)
     }
 }
 
 #[derive(Clone)]
 pub struct Metal {
-    albedo: Vector4,
+    albedo: Arc<dyn Texture>,
     fuzz: f32,
 }
 
 impl Metal {
     pub fn new(albedo: Vector4, fuzz: f32) -> Self {
+        Metal::textured(ConstantTexture::new(albedo), fuzz)
+    }
+
+    pub fn textured<TTexture: Texture + 'static>(albedo: TTexture, fuzz: f32) -> Self {
         Metal {
-            albedo: albedo,
-            fuzz: fuzz,
+            albedo: Arc::new(albedo),
+            fuzz,
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord) -> Option<(Ray, Vector4)> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord, rng: &mut dyn Rng) -> Option<(Ray, Vector4)> {
         let reflected = ray_in.direction().unit_vector().reflect(hit_record.normal);
-        let scattered = Ray::new(hit_record.intersection_point, reflected + (random_in_unit_sphere() * self.fuzz));
+        let scattered = Ray::with_time(hit_record.intersection_point, reflected + (random_in_unit_sphere(rng) * self.fuzz), ray_in.time());
 
         if scattered.direction().dot3(hit_record.normal) > 0.0 {
-            Some((scattered, self.albedo))
+            Some((scattered, self.albedo(hit_record)))
         } else {
             None
         }
     }
+
+    fn albedo(&self, hit_record: &IntersectionRecord) -> Vector4 {
+        self.albedo.value(hit_record.u, hit_record.v, hit_record.intersection_point)
+    }
 }
 
 #[derive(Clone)]
@@ -70,33 +102,91 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord) -> Option<(Ray, Vector4)> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord, rng: &mut dyn Rng) -> Option<(Ray, Vector4)> {
         let attenuation = Vector4::new3(1.0, 1.0, 1.0);
-        let cos_i = ray_in.direction().unit_vector().dot3(hit_record.normal);
-        let (outward_normal, ratio, cosine) =
-            if cos_i > 0.0 {
-                let cosine = self.refractive_index * cos_i / ray_in.direction().length();
-                (-hit_record.normal, self.refractive_index, cosine)
-            } else {
-                let cosine = -cos_i / ray_in.direction().length();
-                (hit_record.normal, 1.0 / self.refractive_index, cosine)
-            };
-
-        let refract_result = refract(ray_in.direction(), outward_normal, ratio);
+        let unit_direction = ray_in.direction().unit_vector();
+
+        // Entering the material from outside, or leaving it
+        let ratio = if hit_record.front_face { 1.0 / self.refractive_index } else { self.refractive_index };
+        let cosine = (-unit_direction.dot3(hit_record.normal)).min(1.0);
+
+        let refract_result = refract(unit_direction, hit_record.normal, ratio);
 
         let reflect_probability = schlick(cosine, self.refractive_index);
-        if rand::random::<f32>() < reflect_probability {
-            let reflected = ray_in.direction().reflect(hit_record.normal);
-            Some((Ray::new(hit_record.intersection_point, reflected), attenuation))
+        if rng.next_f32() < reflect_probability {
+            let reflected = unit_direction.reflect(hit_record.normal);
+            Some((Ray::with_time(hit_record.intersection_point, reflected, ray_in.time()), attenuation))
+        } else if let Some(refracted) = refract_result {
+            Some((Ray::with_time(hit_record.intersection_point, refracted, ray_in.time()), attenuation))
         } else {
-            if let Some(refracted) = refract_result {
-                Some((Ray::new(hit_record.intersection_point, refracted), attenuation))
-            } else {
-                let reflected = ray_in.direction().reflect(hit_record.normal);
-                Some((Ray::new(hit_record.intersection_point, reflected), attenuation))
-            }
+            let reflected = unit_direction.reflect(hit_record.normal);
+            Some((Ray::with_time(hit_record.intersection_point, reflected, ray_in.time()), attenuation))
         }
     }
+
+    /// Glass lets all light through, one way or another
+    fn albedo(&self, _: &IntersectionRecord) -> Vector4 {
+        Vector4::new3(1.0, 1.0, 1.0)
+    }
+}
+
+/// A light source, it emits the same light in every direction and doesn't
+/// scatter anything.
+#[derive(Clone)]
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vector4) -> Self {
+        DiffuseLight::textured(ConstantTexture::new(emit))
+    }
+
+    pub fn textured<TTexture: Texture + 'static>(emit: TTexture) -> Self {
+        DiffuseLight {
+            emit: Arc::new(emit),
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _: &Ray, _: &IntersectionRecord, _: &mut dyn Rng) -> Option<(Ray, Vector4)> {
+        None
+    }
+
+    fn emitted(&self, hit_record: &IntersectionRecord) -> Vector4 {
+        self.emit.value(hit_record.u, hit_record.v, hit_record.intersection_point)
+    }
+}
+
+/// Scatters light equally in every direction, the phase function of fog
+/// and smoke in a `ConstantMedium`.
+#[derive(Clone)]
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Vector4) -> Self {
+        Isotropic::textured(ConstantTexture::new(albedo))
+    }
+
+    pub fn textured<TTexture: Texture + 'static>(albedo: TTexture) -> Self {
+        Isotropic {
+            albedo: Arc::new(albedo),
+        }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, ray_in: &Ray, hit_record: &IntersectionRecord, rng: &mut dyn Rng) -> Option<(Ray, Vector4)> {
+        let scattered = Ray::with_time(hit_record.intersection_point, random_in_unit_sphere(rng), ray_in.time());
+        Some((scattered, self.albedo(hit_record)))
+    }
+
+    fn albedo(&self, hit_record: &IntersectionRecord) -> Vector4 {
+        self.albedo.value(hit_record.u, hit_record.v, hit_record.intersection_point)
+    }
 }
 
 fn schlick(cosine: f32, refractive_index: f32) -> f32 {
@@ -116,17 +206,3 @@ fn refract(vector_in: Vector4, normal: Vector4, ratio: f32) -> Option<Vector4> {
         None
     }
 }
-
-fn random_in_unit_sphere() -> Vector4 {
-    loop {
-        let p = (Vector4::new3(
-            rand::random::<f32>(),
-            rand::random::<f32>(),
-            rand::random::<f32>()
-        ) * 2.0) - Vector4::new3(1.0, 1.0, 1.0);
-
-        if p.length_squared() < 1.0 {
-            return p;
-        }
-    }
-}
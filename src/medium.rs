@@ -0,0 +1,153 @@
+use aabb::Aabb;
+use material::{ Isotropic, Material };
+use rand::Rng;
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+use std::sync::Arc;
+use texture::Texture;
+use triangle::coordinate_system;
+use vector::Vector4;
+
+/// A volume of fog or smoke of constant density filling a boundary, from
+/// chapter 8 of "Ray Tracing: The Next Week".
+///
+/// Rays passing through are scattered at exponentially distributed
+/// distances, so the thicker the volume the more light it catches. The
+/// boundary must be convex, rays are assumed to enter and leave it once.
+pub struct ConstantMedium<TRenderable: Renderable> {
+    boundary: TRenderable,
+    density: f32,
+    phase_function: Arc<dyn Material>,
+}
+
+impl<TRenderable: Renderable> ConstantMedium<TRenderable> {
+    /// A medium scattering light equally in all directions, tinted by
+    /// `albedo`.
+    pub fn new(boundary: TRenderable, density: f32, albedo: Vector4) -> Self {
+        ConstantMedium::with_phase_function(boundary, density, Isotropic::new(albedo))
+    }
+
+    pub fn textured<TTexture: Texture + 'static>(boundary: TRenderable, density: f32, albedo: TTexture) -> Self {
+        ConstantMedium::with_phase_function(boundary, density, Isotropic::textured(albedo))
+    }
+
+    /// A medium scattering light with `phase_function`, which is given hits
+    /// inside the volume rather than on a surface.
+    pub fn with_phase_function<TMaterial: Material + 'static>(boundary: TRenderable, density: f32,
+                                                              phase_function: TMaterial) -> Self {
+        ConstantMedium {
+            boundary,
+            density,
+            phase_function: Arc::new(phase_function),
+        }
+    }
+}
+
+impl<TRenderable: Renderable> Renderable for ConstantMedium<TRenderable> {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        // Find where the whole line enters and leaves the boundary, the ray
+        // may start inside it
+        let entry = self.boundary.intersects(ray, f32::MIN, f32::MAX, rng)?;
+        let exit = self.boundary.intersects(ray, entry.distance + 0.0001, f32::MAX, rng)?;
+
+        let entry_distance = entry.distance.max(distance_min).max(0.0);
+        let exit_distance = exit.distance.min(distance_max);
+        if entry_distance >= exit_distance {
+            return None;
+        }
+
+        let direction = ray.direction();
+        let ray_length = direction.length();
+        let distance_inside = (exit_distance - entry_distance) * ray_length;
+        let scatter_distance = -(1.0 - rng.next_f32()).ln() / self.density;
+        if scatter_distance > distance_inside {
+            return None;
+        }
+
+        // There's no surface, so any normal will do, this one faces the ray
+        let normal = -direction.unit_vector();
+
+        Some(IntersectionRecord::new(
+            ray,
+            entry_distance + scatter_distance / ray_length,
+            normal,
+            (0.0, 0.0),
+            coordinate_system(normal),
+            self.phase_function.clone(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use random::seeded_rng;
+    use sphere::Sphere;
+
+    fn boundary(radius: f32) -> Sphere {
+        Sphere::new(Vector4::new3(0.0, 0.0, 0.0), radius, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn test_dense_medium_scatters_near_the_boundary() {
+        let medium = ConstantMedium::new(boundary(1.0), 10000.0, Vector4::new3(1.0, 1.0, 1.0));
+        let mut rng = seeded_rng(0);
+
+        let ray = Ray::new(Vector4::new3(0.0, 0.0, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        let record = medium.intersects(&ray, 0.001, f32::MAX, &mut rng).unwrap();
+        assert!(record.distance >= 4.0 && record.distance < 4.01);
+        assert!(record.front_face);
+
+        // A ray starting inside scatters right away
+        let inside = Ray::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 0.0, -1.0));
+        assert!(medium.intersects(&inside, 0.001, f32::MAX, &mut rng).unwrap().distance < 0.01);
+
+        let miss = Ray::new(Vector4::new3(0.0, 2.0, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        assert!(medium.intersects(&miss, 0.001, f32::MAX, &mut rng).is_none());
+    }
+
+    #[test]
+    fn test_thin_medium_transmittance() {
+        let density = 0.5;
+        let medium = ConstantMedium::new(boundary(1.0), density, Vector4::new3(1.0, 1.0, 1.0));
+        let mut rng = seeded_rng(1);
+
+        // Rays straight through the middle travel 2 units inside, so the
+        // fraction passing through should be close to e^(-density * 2)
+        let count = 10000;
+        let ray = Ray::new(Vector4::new3(0.0, 0.0, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        let passed = (0..count).filter(|_| medium.intersects(&ray, 0.001, f32::MAX, &mut rng).is_none()).count();
+
+        let expected = (-density * 2.0).exp();
+        assert!((passed as f32 / count as f32 - expected).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_scatter_distances_are_exponential() {
+        let density = 2.0;
+        let medium = ConstantMedium::new(boundary(1000.0), density, Vector4::new3(1.0, 1.0, 1.0));
+        let mut rng = seeded_rng(2);
+
+        // The same ray every time, only the generator differs
+        let ray = Ray::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 0.0, -2.0));
+        let count = 20000;
+        let distances: Vec<f32> = (0..count)
+            .map(|_| medium.intersects(&ray, 0.0, f32::MAX, &mut rng).unwrap().distance * 2.0)
+            .collect();
+
+        let mean = distances.iter().sum::<f32>() / count as f32;
+        assert!((mean - 1.0 / density).abs() < 0.01);
+
+        // Exponential distances pass any given length with probability
+        // e^(-density * length)
+        for &length in &[0.1, 0.5, 1.0, 2.0] {
+            let beyond = distances.iter().filter(|&&distance| distance > length).count();
+            assert!((beyond as f32 / count as f32 - (-density * length).exp()).abs() < 0.01);
+        }
+    }
+}
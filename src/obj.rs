@@ -0,0 +1,617 @@
+use material::{ Dielectric, Lambertian, Material, Metal };
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{ self, BufRead, BufReader };
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use triangle::TriangleMesh;
+use vector::Vector4;
+
+/// The reasons an OBJ or MTL file can fail to load. Line numbers start at 1.
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+
+    /// A statement had the wrong number of values or a value couldn't be
+    /// parsed.
+    Parse { line: usize, message: String },
+
+    /// A face referred to a vertex, texture coordinate or normal that
+    /// hasn't been defined.
+    IndexOutOfRange { line: usize, index: i64 },
+
+    /// `usemtl` named a material that isn't in any loaded library.
+    UnknownMaterial { line: usize, name: String },
+
+    /// An error in a material library referenced by `mtllib`.
+    MaterialLibrary { library: String, error: Box<ObjError> },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjError::Io(ref error) => write!(f, "{}", error),
+            ObjError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+            ObjError::IndexOutOfRange { line, index } => write!(f, "line {}: index {} is out of range", line, index),
+            ObjError::UnknownMaterial { line, ref name } => write!(f, "line {}: unknown material '{}'", line, name),
+            ObjError::MaterialLibrary { ref library, ref error } => write!(f, "{}: {}", library, error),
+        }
+    }
+}
+
+impl error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ObjError::Io(ref error) => Some(error),
+            ObjError::MaterialLibrary { ref error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> Self {
+        ObjError::Io(error)
+    }
+}
+
+/// A named group of faces sharing one material.
+pub struct ObjGroup {
+    pub name: String,
+    pub mesh: TriangleMesh,
+}
+
+/// Loads an OBJ file and any material libraries it references, relative to
+/// the OBJ file's directory.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Vec<ObjGroup>, ObjError> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let file = BufReader::new(File::open(path)?);
+
+    parse_obj(file, |library| File::open(directory.join(library)).map(BufReader::new))
+}
+
+/// Parses OBJ data into one mesh per group and material.
+///
+/// Polygons are triangulated as fans around their first vertex. A group
+/// only has normals or texture coordinates if every one of its face
+/// vertices specifies them. `open_library` is called to read the material
+/// libraries named by `mtllib` statements.
+pub fn parse_obj<TRead, TLibrary, TOpen>(reader: TRead, mut open_library: TOpen) -> Result<Vec<ObjGroup>, ObjError>
+    where TRead: BufRead, TLibrary: BufRead, TOpen: FnMut(&str) -> io::Result<TLibrary> {
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+
+    let mut materials = HashMap::new();
+    let mut groups = Vec::new();
+    let mut current = GroupBuilder::new(String::from("default"), default_material());
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line?;
+        let mut tokens = statement(&line);
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let values = parse_finite(&arguments, 3, 4, line_number)?;
+                positions.push(Vector4::new3(values[0], values[1], values[2]));
+            }
+            "vt" => {
+                let values = parse_finite(&arguments, 1, 3, line_number)?;
+                uvs.push((values[0], *values.get(1).unwrap_or(&0.0)));
+            }
+            "vn" => {
+                let values = parse_finite(&arguments, 3, 3, line_number)?;
+                let normal = Vector4::new3(values[0], values[1], values[2]);
+
+                // A zero length normal has no direction to shade with, and one
+                // too long to square can't be normalised either
+                let length = normal.length();
+                if length == 0.0 || !length.is_finite() {
+                    return Err(parse_error(line_number, "a normal needs a non-zero, finite length"));
+                }
+
+                normals.push(normal);
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(parse_error(line_number, "a face needs at least three vertices"));
+                }
+
+                let mut face = Vec::with_capacity(arguments.len());
+                for argument in arguments.iter() {
+                    face.push(parse_face_vertex(argument, positions.len(), uvs.len(), normals.len(), line_number)?);
+                }
+
+                current.add_face(&face, &positions, &uvs, &normals);
+            }
+            "g" | "o" => {
+                let name = if arguments.is_empty() { String::from("default") } else { arguments.join(" ") };
+                let material = current.material.clone();
+                current.finish(&mut groups);
+                current = GroupBuilder::new(name, material);
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                let material = match materials.get(&name) {
+                    Some(material) => Arc::clone(material),
+                    None => return Err(ObjError::UnknownMaterial { line: line_number, name }),
+                };
+
+                let group_name = current.name.clone();
+                current.finish(&mut groups);
+                current = GroupBuilder::new(group_name, material);
+            }
+            "mtllib" => {
+                for library in arguments.iter() {
+                    let reader = open_library(library).map_err(|error| ObjError::MaterialLibrary {
+                        library: library.to_string(),
+                        error: Box::new(ObjError::Io(error)),
+                    })?;
+
+                    let library_materials = parse_mtl(reader).map_err(|error| ObjError::MaterialLibrary {
+                        library: library.to_string(),
+                        error: Box::new(error),
+                    })?;
+
+                    materials.extend(library_materials);
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry aren't
+            // supported.
+            _ => {}
+        }
+    }
+
+    current.finish(&mut groups);
+
+    Ok(groups)
+}
+
+/// Parses an MTL material library.
+///
+/// Each material is mapped onto the closest of the built in materials:
+///
+///  * Transparent materials (`d` below 1, `Tr` above 0, or a refracting
+///    `illum` model) become `Dielectric`, using `Ni` as the refractive
+///    index.
+///  * Reflective materials (`illum 3`, or a specular colour `Ks` brighter
+///    than the diffuse colour `Kd`) become `Metal` with an albedo of `Ks`.
+///    The fuzz is derived from the specular exponent `Ns`, a higher
+///    exponent gives a sharper reflection.
+///  * Everything else becomes `Lambertian` with an albedo of `Kd`.
+pub fn parse_mtl<TRead: BufRead>(reader: TRead) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<MtlMaterial> = None;
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = line?;
+        let mut tokens = statement(&line);
+
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+
+        let arguments: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            if let Some(material) = current.take() {
+                materials.insert(material.name.clone(), material.into_material());
+            }
+
+            if arguments.is_empty() {
+                return Err(parse_error(line_number, "newmtl needs a name"));
+            }
+
+            current = Some(MtlMaterial::new(arguments.join(" ")));
+            continue;
+        }
+
+        let material = match current {
+            Some(ref mut material) => material,
+            None => return Err(parse_error(line_number, "material property given before newmtl")),
+        };
+
+        match keyword {
+            "Kd" => material.diffuse = parse_colour(&arguments, line_number)?,
+            "Ks" => material.specular = parse_colour(&arguments, line_number)?,
+            "Ns" => material.specular_exponent = parse_finite(&arguments, 1, 1, line_number)?[0],
+            "Ni" => material.refractive_index = parse_finite(&arguments, 1, 1, line_number)?[0],
+            "d" => material.dissolve = parse_finite(&arguments, 1, 1, line_number)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_finite(&arguments, 1, 1, line_number)?[0],
+            "illum" => material.illumination = parse_values::<u32>(&arguments, 1, 1, line_number)?[0],
+            // Ambient and emissive colours and texture maps are ignored
+            _ => {}
+        }
+    }
+
+    if let Some(material) = current.take() {
+        materials.insert(material.name.clone(), material.into_material());
+    }
+
+    Ok(materials)
+}
+
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Vector4::new3(0.8, 0.8, 0.8)))
+}
+
+/// Splits a line into whitespace separated tokens, ignoring comments.
+fn statement(line: &str) -> ::std::str::SplitWhitespace<'_> {
+    let line = match line.find('#') {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+
+    line.split_whitespace()
+}
+
+fn parse_error(line: usize, message: &str) -> ObjError {
+    ObjError::Parse { line, message: message.to_string() }
+}
+
+fn parse_values<T: FromStr>(arguments: &[&str], min: usize, max: usize, line: usize) -> Result<Vec<T>, ObjError> {
+    if arguments.len() < min || arguments.len() > max {
+        return Err(ObjError::Parse {
+            line,
+            message: format!("expected between {} and {} values, found {}", min, max, arguments.len()),
+        });
+    }
+
+    arguments.iter()
+        .map(|argument| argument.parse::<T>().map_err(|_| ObjError::Parse {
+            line,
+            message: format!("'{}' is not a valid number", argument),
+        }))
+        .collect()
+}
+
+/// Parses the values of a vertex position, uv, normal or material property,
+/// which must be finite for the geometry and materials built from them to
+/// be.
+fn parse_finite(arguments: &[&str], min: usize, max: usize, line: usize) -> Result<Vec<f32>, ObjError> {
+    let values = parse_values::<f32>(arguments, min, max, line)?;
+
+    match arguments.iter().zip(values.iter()).find(|&(_, value)| !value.is_finite()) {
+        Some((argument, _)) => Err(ObjError::Parse { line, message: format!("'{}' is not a finite number", argument) }),
+        None => Ok(values),
+    }
+}
+
+fn parse_colour(arguments: &[&str], line: usize) -> Result<Vector4, ObjError> {
+    let values = parse_finite(arguments, 1, 3, line)?;
+
+    // A single value is used for all three channels
+    if values.len() == 1 {
+        Ok(Vector4::new3(values[0], values[0], values[0]))
+    } else if values.len() == 3 {
+        Ok(Vector4::new3(values[0], values[1], values[2]))
+    } else {
+        Err(parse_error(line, "a colour needs one or three values"))
+    }
+}
+
+/// A face vertex, as zero based indices into the position, uv and normal
+/// lists.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Parses a face vertex of the form `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+fn parse_face_vertex(argument: &str, positions: usize, uvs: usize, normals: usize, line: usize)
+    -> Result<FaceVertex, ObjError> {
+
+    let mut parts = argument.split('/');
+    let position = parse_index(parts.next(), positions, line)?
+        .ok_or_else(|| parse_error(line, "a face vertex needs a position"))?;
+    let uv = parse_index(parts.next(), uvs, line)?;
+    let normal = parse_index(parts.next(), normals, line)?;
+
+    if parts.next().is_some() {
+        return Err(ObjError::Parse { line, message: format!("'{}' is not a valid face vertex", argument) });
+    }
+
+    Ok(FaceVertex { position, uv, normal })
+}
+
+/// Resolves a one based index, negative indices count back from the most
+/// recently defined element.
+fn parse_index(part: Option<&str>, count: usize, line: usize) -> Result<Option<usize>, ObjError> {
+    let part = match part {
+        Some(part) if !part.is_empty() => part,
+        _ => return Ok(None),
+    };
+
+    let index = part.parse::<i64>()
+        .map_err(|_| ObjError::Parse { line, message: format!("'{}' is not a valid index", part) })?;
+
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::IndexOutOfRange { line, index });
+    }
+
+    Ok(Some(resolved as usize))
+}
+
+/// Collects the faces of one group, giving each distinct face vertex its
+/// own entry in the mesh's vertex buffers.
+struct GroupBuilder {
+    name: String,
+    material: Arc<dyn Material>,
+    vertex_indices: HashMap<FaceVertex, usize>,
+    positions: Vec<Vector4>,
+    uvs: Vec<Option<(f32, f32)>>,
+    normals: Vec<Option<Vector4>>,
+    triangles: Vec<[usize; 3]>,
+}
+
+impl GroupBuilder {
+    fn new(name: String, material: Arc<dyn Material>) -> Self {
+        GroupBuilder {
+            name,
+            material,
+            vertex_indices: HashMap::new(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            triangles: Vec::new(),
+        }
+    }
+
+    fn add_face(&mut self, face: &[FaceVertex], positions: &[Vector4], uvs: &[(f32, f32)], normals: &[Vector4]) {
+        let indices: Vec<usize> = face.iter().map(|vertex| self.vertex_index(*vertex, positions, uvs, normals)).collect();
+
+        for i in 1..indices.len() - 1 {
+            self.triangles.push([indices[0], indices[i], indices[i + 1]]);
+        }
+    }
+
+    fn vertex_index(&mut self, vertex: FaceVertex, positions: &[Vector4], uvs: &[(f32, f32)], normals: &[Vector4]) -> usize {
+        if let Some(&index) = self.vertex_indices.get(&vertex) {
+            return index;
+        }
+
+        let index = self.positions.len();
+        self.positions.push(positions[vertex.position]);
+        self.uvs.push(vertex.uv.map(|uv| uvs[uv]));
+        self.normals.push(vertex.normal.map(|normal| normals[normal].unit_vector()));
+        self.vertex_indices.insert(vertex, index);
+
+        index
+    }
+
+    fn finish(self, groups: &mut Vec<ObjGroup>) {
+        if self.triangles.is_empty() {
+            return;
+        }
+
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<_>>>();
+        let normals = self.normals.into_iter().collect::<Option<Vec<_>>>();
+
+        groups.push(ObjGroup {
+            name: self.name,
+            mesh: TriangleMesh::with_shared_material(self.positions, normals, uvs, self.triangles, self.material),
+        });
+    }
+}
+
+struct MtlMaterial {
+    name: String,
+    diffuse: Vector4,
+    specular: Vector4,
+    specular_exponent: f32,
+    refractive_index: f32,
+    dissolve: f32,
+    illumination: u32,
+}
+
+impl MtlMaterial {
+    fn new(name: String) -> Self {
+        MtlMaterial {
+            name,
+            diffuse: Vector4::new3(0.8, 0.8, 0.8),
+            specular: Vector4::new3(0.0, 0.0, 0.0),
+            specular_exponent: 0.0,
+            refractive_index: 1.5,
+            dissolve: 1.0,
+            illumination: 2,
+        }
+    }
+
+    fn into_material(self) -> Arc<dyn Material> {
+        let refracts = matches!(self.illumination, 4 | 6 | 7 | 9);
+
+        if self.dissolve < 1.0 || refracts {
+            return Arc::new(Dielectric::new(self.refractive_index));
+        }
+
+        let brightness = |colour: Vector4| colour.x + colour.y + colour.z;
+        if self.illumination == 3 || brightness(self.specular) > brightness(self.diffuse) {
+            let fuzz = 1.0 - (self.specular_exponent / 1000.0).clamp(0.0, 1.0);
+            return Arc::new(Metal::new(self.specular, fuzz));
+        }
+
+        Arc::new(Lambertian::new(self.diffuse))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use random::seeded_rng;
+    use ray::Ray;
+    use renderable::{ IntersectionRecord, Renderable };
+    use std::io::Cursor;
+
+    fn no_libraries(_: &str) -> io::Result<Cursor<&'static str>> {
+        Err(io::Error::new(io::ErrorKind::NotFound, "no material libraries"))
+    }
+
+    #[test]
+    fn test_parse_quad() {
+        let obj = "
+            # A unit square in the xy plane
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vt 0 1
+            vn 0 0 1
+            g square
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+        ";
+
+        let groups = parse_obj(Cursor::new(obj), no_libraries).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "square");
+
+        let mesh = &groups[0].mesh;
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.indices(), &[[0, 1, 2], [0, 2, 3]]);
+        assert!(mesh.normals().is_some());
+        assert_eq!(mesh.uvs().unwrap()[2], (1.0, 1.0));
+    }
+
+    fn hit(group: &ObjGroup, ray: &Ray) -> IntersectionRecord {
+        group.mesh.intersects(ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap()
+    }
+
+    fn assert_colour(colour: Vector4, expected: (f32, f32, f32)) {
+        assert_eq!((colour.x, colour.y, colour.z), expected);
+    }
+
+    #[test]
+    fn test_parse_negative_indices_and_materials() {
+        let obj = "
+            mtllib scene.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            f 1 2 3
+            usemtl glass
+            f -3 -2 -1
+            usemtl chrome
+            f 1 2 3
+            usemtl paint
+            f 1 2 3
+        ";
+
+        let mtl = "
+            newmtl glass
+            Kd 1 1 1
+            d 0.2
+            Ni 1.33
+            newmtl chrome
+            Kd 0.1 0.1 0.1
+            Ks 0.9 0.9 0.9
+            Ns 900
+            newmtl paint
+            Kd 0.2 0.4 0.6
+        ";
+
+        let groups = parse_obj(Cursor::new(obj), |library| {
+            assert_eq!(library, "scene.mtl");
+            Ok(Cursor::new(mtl))
+        }).unwrap();
+
+        assert_eq!(groups.len(), 4);
+        assert!(groups[0].mesh.normals().is_none());
+
+        // Straight down onto the triangle, which faces up the z axis
+        let ray = Ray::new(Vector4::new3(0.25, 0.25, 1.0), Vector4::new3(0.0, 0.0, -1.0));
+        let mut rng = seeded_rng(0);
+
+        // Faces before any usemtl get the default Lambertian
+        let record = hit(&groups[0], &ray);
+        assert_colour(record.material.albedo(&record), (0.8, 0.8, 0.8));
+
+        // Glass is a Dielectric, letting all light through and refracting
+        // some of it through the surface
+        let record = hit(&groups[1], &ray);
+        assert_colour(record.material.albedo(&record), (1.0, 1.0, 1.0));
+        let scattered: Vec<(Ray, Vector4)> = (0..100).filter_map(|_| record.material.scatter(&ray, &record, &mut rng)).collect();
+        assert_eq!(scattered.len(), 100);
+        assert!(scattered.iter().any(|(ray, _)| ray.direction().z < 0.0));
+        for &(_, attenuation) in scattered.iter() {
+            assert_colour(attenuation, (1.0, 1.0, 1.0));
+        }
+
+        // Chrome is a Metal with an albedo of Ks and a fuzz of 0.1 from Ns,
+        // reflecting close to the mirror direction
+        let record = hit(&groups[2], &ray);
+        assert_colour(record.material.albedo(&record), (0.9, 0.9, 0.9));
+        for _ in 0..100 {
+            let (scattered, attenuation) = record.material.scatter(&ray, &record, &mut rng).unwrap();
+            let direction = scattered.direction();
+            assert!(direction.x.abs() <= 0.1 && direction.y.abs() <= 0.1 && direction.z >= 0.9);
+            assert_colour(attenuation, (0.9, 0.9, 0.9));
+        }
+
+        // Anything else is a Lambertian with an albedo of Kd
+        let record = hit(&groups[3], &ray);
+        assert_colour(record.material.albedo(&record), (0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        match parse_obj(Cursor::new("v 0 0 0\nv 1 0 0\nf 1 2 3"), no_libraries) {
+            Err(ObjError::IndexOutOfRange { line: 3, index: 3 }) => {}
+            _ => panic!("expected an index error"),
+        }
+
+        match parse_obj(Cursor::new("v 0 zero 0"), no_libraries) {
+            Err(ObjError::Parse { line: 1, .. }) => {}
+            _ => panic!("expected a parse error"),
+        }
+
+        for source in &["v 0 inf 0", "v 0 0 0\nvn NaN 0 1", "v 0 0 0\nvt 0.5 -inf"] {
+            match parse_obj(Cursor::new(*source), no_libraries) {
+                Err(ObjError::Parse { .. }) => {}
+                _ => panic!("expected non-finite values to be rejected in {:?}", source),
+            }
+        }
+
+        for source in &["vn 0 0 0", "vn 1e-30 0 0", "vn 1e30 1e30 0"] {
+            match parse_obj(Cursor::new(*source), no_libraries) {
+                Err(ObjError::Parse { line: 1, .. }) => {}
+                _ => panic!("expected a normal that can't be normalised to be rejected in {:?}", source),
+            }
+        }
+
+        for mtl in &["newmtl a\nKd 0.5 NaN 0.5", "newmtl a\nKs inf", "newmtl a\nNs -inf",
+                     "newmtl a\nNi NaN", "newmtl a\nd inf", "newmtl a\nTr NaN"] {
+            match parse_mtl(Cursor::new(*mtl)) {
+                Err(ObjError::Parse { line: 2, .. }) => {}
+                _ => panic!("expected non-finite values to be rejected in {:?}", mtl),
+            }
+        }
+
+        match parse_obj(Cursor::new("usemtl missing"), no_libraries) {
+            Err(ObjError::UnknownMaterial { line: 1, ref name }) if name == "missing" => {}
+            _ => panic!("expected an unknown material error"),
+        }
+
+        match parse_obj(Cursor::new("mtllib missing.mtl"), no_libraries) {
+            Err(ObjError::MaterialLibrary { ref library, .. }) if library == "missing.mtl" => {}
+            _ => panic!("expected a material library error"),
+        }
+    }
+}
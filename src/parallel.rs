@@ -0,0 +1,98 @@
+use vector::Vector4;
+use std::sync::Mutex;
+use std::thread;
+
+/// The number of worker threads to use when none is specified, one per
+/// available core.
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// Renders an image by evaluating `pixel_colour` for every pixel, splitting
+/// the work across `threads` worker threads.
+///
+/// Rows are handed out one at a time so that expensive parts of the image
+/// don't leave the other workers idle.  The returned buffer holds RGB
+/// triples laid out the same way the examples have always produced them:
+/// starting at the top left, left to right, top to bottom.  `pixel_colour`
+/// is called with screen coordinates, so `y == 0` is the bottom row.
+pub fn render_parallel<F>(width: usize, height: usize, threads: usize, pixel_colour: F) -> Vec<f32>
+    where F: Fn(usize, usize) -> Vector4 + Sync {
+
+    map_pixels_parallel(width, height, threads, pixel_colour)
+        .into_iter()
+        .flat_map(|colour| [colour.x, colour.y, colour.z])
+        .collect()
+}
+
+/// Evaluates `pixel` for every pixel in parallel like `render_parallel`,
+/// for renders which produce more than a colour per pixel. The results are
+/// laid out one per pixel, starting at the top left.
+pub fn map_pixels_parallel<T, F>(width: usize, height: usize, threads: usize, pixel: F) -> Vec<T>
+    where T: Send, F: Fn(usize, usize) -> T + Sync {
+
+    let mut results: Vec<Option<T>> = (0..width * height).map(|_| None).collect();
+
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    {
+        let rows = Mutex::new(results.chunks_mut(width).enumerate());
+        let pixel = &pixel;
+        let rows = &rows;
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(move || {
+                    loop {
+                        let next = rows.lock().unwrap().next();
+                        let (row, row_data) = match next {
+                            Some(next) => next,
+                            None => break,
+                        };
+
+                        let y = height - 1 - row;
+                        for (x, result) in row_data.iter_mut().enumerate() {
+                            *result = Some(pixel(x, y));
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    results.into_iter().map(|result| result.unwrap()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_top_row_first() {
+        // The first result is the top left, which is the highest y
+        let pixels = map_pixels_parallel(3, 2, 2, |x, y| (x, y));
+        assert_eq!(pixels, vec![(0, 1), (1, 1), (2, 1), (0, 0), (1, 0), (2, 0)]);
+
+        let image = render_parallel(2, 2, 2, |x, y| Vector4::new3(x as f32, y as f32, 0.0));
+        assert_eq!(image, vec![0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_empty_image() {
+        assert!(map_pixels_parallel(0, 4, 2, |x, y| (x, y)).is_empty());
+        assert!(map_pixels_parallel(4, 0, 2, |x, y| (x, y)).is_empty());
+        assert!(render_parallel(0, 0, 2, |_, _| Vector4::new3(1.0, 1.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_thread_count_does_not_change_the_image() {
+        let pixel = |x: usize, y: usize| x * 1000 + y;
+
+        let single_threaded = map_pixels_parallel(37, 23, 1, pixel);
+        for &threads in &[0, 2, 8, 64] {
+            assert_eq!(map_pixels_parallel(37, 23, threads, pixel), single_threaded);
+        }
+    }
+}
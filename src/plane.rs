@@ -0,0 +1,81 @@
+use aabb::Aabb;
+use material::Material;
+use rand::Rng;
+use vector::Vector4;
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+use std::sync::Arc;
+use triangle::coordinate_system;
+
+/// An infinite plane through `point`, facing along `normal`.
+///
+/// Surface coordinates are distances across the plane from `point`, so an
+/// image texture which repeats tiles the plane once per unit.
+pub struct Plane {
+    point: Vector4,
+    normal: Vector4,
+    tangent: Vector4,
+    bitangent: Vector4,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new<TMaterial: Material + 'static>(point: Vector4, normal: Vector4, material: TMaterial) -> Self {
+        let normal = normal.unit_vector();
+        let (tangent, bitangent) = coordinate_system(normal);
+
+        Plane {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material: Arc::new(material),
+        }
+    }
+}
+
+impl Renderable for Plane {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, _rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        // Rays parallel to the plane give an infinite or NaN distance, which
+        // the range check rejects
+        let distance = (self.point - ray.origin()).dot3(self.normal) / ray.direction().dot3(self.normal);
+        if !(distance > distance_min && distance < distance_max) {
+            return None;
+        }
+
+        let offset = ray.point_at_distance(distance) - self.point;
+        let uv = (offset.dot3(self.tangent), offset.dot3(self.bitangent));
+
+        Some(IntersectionRecord::new(ray, distance, self.normal, uv, (self.tangent, self.bitangent), self.material.clone()))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use random::seeded_rng;
+
+    #[test]
+    fn test_plane() {
+        let plane = Plane::new(Vector4::new3(0.0, -1.0, 0.0), Vector4::new3(0.0, 2.0, 0.0),
+                               Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)));
+
+        let ray = Ray::new(Vector4::new3(100.0, 1.0, -50.0), Vector4::new3(0.0, -1.0, 0.0));
+        let record = plane.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert_eq!(record.distance, 2.0);
+        assert_eq!(record.normal.y, 1.0);
+        assert!(record.front_face);
+
+        let below = Ray::new(Vector4::new3(0.0, -3.0, 0.0), Vector4::new3(0.0, 1.0, 0.0));
+        assert!(!plane.intersects(&below, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap().front_face);
+
+        let parallel = Ray::new(Vector4::new3(0.0, 1.0, 0.0), Vector4::new3(1.0, 0.0, 0.0));
+        assert!(plane.intersects(&parallel, 0.001, f32::MAX, &mut seeded_rng(0)).is_none());
+        assert!(plane.bounding_box().is_none());
+    }
+}
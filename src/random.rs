@@ -0,0 +1,55 @@
+use rand::{ Rng, SeedableRng, XorShiftRng };
+use std::f32::consts::{ FRAC_PI_2, FRAC_PI_4, PI };
+use vector::Vector4;
+
+/// Creates a generator from a single seed value.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    let a = mix(seed);
+    let b = mix(a);
+
+    // An all zero seed is the one state a xorshift generator can't leave.
+    let seed = [(a >> 32) as u32, a as u32, (b >> 32) as u32, (b as u32) | 1];
+    XorShiftRng::from_seed(seed)
+}
+
+// The splitmix64 finaliser, it spreads every input bit across the output so
+// that neighbouring pixels get unrelated seeds.
+pub(crate) fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A random point inside the unit sphere, from exactly three numbers drawn
+/// from `rng` so that samplers can keep every bounce's dimensions apart.
+pub fn random_in_unit_sphere(rng: &mut dyn Rng) -> Vector4 {
+    let z = 1.0 - 2.0 * rng.next_f32();
+    let phi = 2.0 * PI * rng.next_f32();
+    let radius = rng.next_f32().cbrt();
+
+    let ring = (1.0 - z * z).max(0.0).sqrt();
+    Vector4::new3(ring * phi.cos(), ring * phi.sin(), z) * radius
+}
+
+/// A random point inside the unit disk in the xy plane, from exactly two
+/// numbers drawn from `rng`.
+///
+/// This is Shirley and Chiu's concentric mapping, it squashes squares onto
+/// rings so that well spread out numbers give well spread out points.
+pub fn random_in_unit_disk(rng: &mut dyn Rng) -> Vector4 {
+    let a = rng.next_f32() * 2.0 - 1.0;
+    let b = rng.next_f32() * 2.0 - 1.0;
+
+    if a == 0.0 && b == 0.0 {
+        return Vector4::new3(0.0, 0.0, 0.0);
+    }
+
+    let (radius, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+
+    Vector4::new3(radius * theta.cos(), radius * theta.sin(), 0.0)
+}
@@ -6,17 +6,27 @@ use vector::Vector4;
 /// Where p is a 3D position along a line in three dimensions
 /// A, is the ray origin, and B is the ray direction.
 /// t is some distance from the origin
+///
+/// Each ray also carries the moment in time it was cast, so moving objects
+/// can be sampled across the camera shutter interval.
 pub struct Ray {
     origin:    Vector4, // 'a'
     direction: Vector4, // 'b'
+    time:      f32,
 }
 
 impl Ray {
 
+    /// A ray cast at time 0.0.
     pub fn new(origin: Vector4, direction: Vector4) -> Self {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Vector4, direction: Vector4, time: f32) -> Self {
         Ray {
-            origin: origin,
-            direction: direction
+            origin,
+            direction,
+            time,
         }
     }
 
@@ -31,4 +41,8 @@ impl Ray {
     pub fn direction(&self) -> Vector4 {
         self.direction
     }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
 }
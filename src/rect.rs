@@ -0,0 +1,271 @@
+use aabb::Aabb;
+use material::Material;
+use rand::Rng;
+use vector::Vector4;
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+use std::sync::Arc;
+
+/// How far the bounding box of a rectangle is padded either side of its
+/// plane, so it never has zero thickness.
+const RECT_PADDING: f32 = 0.0001;
+
+/// A rectangle lying in the plane where coordinate `axes.2` equals `k`,
+/// spanning `min` to `max` along the other two axes.
+struct AxisRect {
+    axes: (usize, usize, usize),
+    min: (f32, f32),
+    max: (f32, f32),
+    k: f32,
+    flipped: bool,
+    material: Arc<dyn Material>,
+}
+
+impl AxisRect {
+    fn new(axes: (usize, usize, usize), min: (f32, f32), max: (f32, f32), k: f32,
+           material: Arc<dyn Material>) -> Self {
+        AxisRect {
+            axes,
+            min: (min.0.min(max.0), min.1.min(max.1)),
+            max: (min.0.max(max.0), min.1.max(max.1)),
+            k,
+            flipped: false,
+            material,
+        }
+    }
+
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, _rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        let (a, b, c) = self.axes;
+        let origin = ray.origin();
+        let direction = ray.direction();
+
+        // Rays parallel to the plane divide by zero and give an infinite or
+        // NaN distance, which the range check rejects
+        let distance = (self.k - origin[c]) / direction[c];
+        if !(distance > distance_min && distance < distance_max) {
+            return None;
+        }
+
+        let point = ray.point_at_distance(distance);
+        if point[a] < self.min.0 || point[a] > self.max.0 || point[b] < self.min.1 || point[b] > self.max.1 {
+            return None;
+        }
+
+        let (width, height) = (self.max.0 - self.min.0, self.max.1 - self.min.1);
+        let uv = ((point[a] - self.min.0) / width, (point[b] - self.min.1) / height);
+        let tangents = (axis_vector(a, width), axis_vector(b, height));
+        let outward_normal = axis_vector(c, if self.flipped { -1.0 } else { 1.0 });
+
+        Some(IntersectionRecord::new(ray, distance, outward_normal, uv, tangents, self.material.clone()))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let (a, b, c) = self.axes;
+        let min = axis_vector(a, self.min.0) + axis_vector(b, self.min.1) + axis_vector(c, self.k - RECT_PADDING);
+        let max = axis_vector(a, self.max.0) + axis_vector(b, self.max.1) + axis_vector(c, self.k + RECT_PADDING);
+        Aabb::new(min, max)
+    }
+}
+
+/// A vector of length `value` along `axis`.
+fn axis_vector(axis: usize, value: f32) -> Vector4 {
+    let mut coordinates = [0.0; 3];
+    coordinates[axis] = value;
+    Vector4::new3(coordinates[0], coordinates[1], coordinates[2])
+}
+
+/// A rectangle in the plane z = `k`, facing +z. `u` runs along x and `v`
+/// along y.
+pub struct XYRect {
+    rect: AxisRect,
+}
+
+impl XYRect {
+    pub fn new<TMaterial: Material + 'static>(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: TMaterial) -> Self {
+        XYRect::with_shared_material(x0, x1, y0, y1, k, Arc::new(material))
+    }
+
+    pub fn with_shared_material(x0: f32, x1: f32, y0: f32, y1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        XYRect {
+            rect: AxisRect::new((0, 1, 2), (x0, y0), (x1, y1), k, material),
+        }
+    }
+
+    /// Faces the rectangle towards -z instead.
+    pub fn flip_normal(mut self) -> Self {
+        self.rect.flipped = !self.rect.flipped;
+        self
+    }
+}
+
+impl Renderable for XYRect {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        self.rect.intersects(ray, distance_min, distance_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.rect.bounding_box())
+    }
+}
+
+/// A rectangle in the plane y = `k`, facing +y. `u` runs along z and `v`
+/// along x.
+pub struct XZRect {
+    rect: AxisRect,
+}
+
+impl XZRect {
+    pub fn new<TMaterial: Material + 'static>(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: TMaterial) -> Self {
+        XZRect::with_shared_material(x0, x1, z0, z1, k, Arc::new(material))
+    }
+
+    pub fn with_shared_material(x0: f32, x1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        XZRect {
+            rect: AxisRect::new((2, 0, 1), (z0, x0), (z1, x1), k, material),
+        }
+    }
+
+    /// Faces the rectangle towards -y instead.
+    pub fn flip_normal(mut self) -> Self {
+        self.rect.flipped = !self.rect.flipped;
+        self
+    }
+}
+
+impl Renderable for XZRect {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        self.rect.intersects(ray, distance_min, distance_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.rect.bounding_box())
+    }
+}
+
+/// A rectangle in the plane x = `k`, facing +x. `u` runs along y and `v`
+/// along z.
+pub struct YZRect {
+    rect: AxisRect,
+}
+
+impl YZRect {
+    pub fn new<TMaterial: Material + 'static>(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: TMaterial) -> Self {
+        YZRect::with_shared_material(y0, y1, z0, z1, k, Arc::new(material))
+    }
+
+    pub fn with_shared_material(y0: f32, y1: f32, z0: f32, z1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        YZRect {
+            rect: AxisRect::new((1, 2, 0), (y0, z0), (y1, z1), k, material),
+        }
+    }
+
+    /// Faces the rectangle towards -x instead.
+    pub fn flip_normal(mut self) -> Self {
+        self.rect.flipped = !self.rect.flipped;
+        self
+    }
+}
+
+impl Renderable for YZRect {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        self.rect.intersects(ray, distance_min, distance_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.rect.bounding_box())
+    }
+}
+
+/// An axis-aligned box between the `min` and `max` corners, built from six
+/// rectangles facing outwards.
+pub struct AxisAlignedBox {
+    min: Vector4,
+    max: Vector4,
+    sides: Vec<Box<dyn Renderable>>,
+}
+
+impl AxisAlignedBox {
+    pub fn new<TMaterial: Material + 'static>(min: Vector4, max: Vector4, material: TMaterial) -> Self {
+        AxisAlignedBox::with_shared_material(min, max, Arc::new(material))
+    }
+
+    pub fn with_shared_material(min: Vector4, max: Vector4, material: Arc<dyn Material>) -> Self {
+        let (p0, p1) = (
+            Vector4::new3(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            Vector4::new3(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        );
+
+        let sides: Vec<Box<dyn Renderable>> = vec![
+            Box::new(XYRect::with_shared_material(p0.x, p1.x, p0.y, p1.y, p1.z, material.clone())),
+            Box::new(XYRect::with_shared_material(p0.x, p1.x, p0.y, p1.y, p0.z, material.clone()).flip_normal()),
+            Box::new(XZRect::with_shared_material(p0.x, p1.x, p0.z, p1.z, p1.y, material.clone())),
+            Box::new(XZRect::with_shared_material(p0.x, p1.x, p0.z, p1.z, p0.y, material.clone()).flip_normal()),
+            Box::new(YZRect::with_shared_material(p0.y, p1.y, p0.z, p1.z, p1.x, material.clone())),
+            Box::new(YZRect::with_shared_material(p0.y, p1.y, p0.z, p1.z, p0.x, material).flip_normal()),
+        ];
+
+        AxisAlignedBox {
+            min: p0,
+            max: p1,
+            sides,
+        }
+    }
+}
+
+impl Renderable for AxisAlignedBox {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        self.sides[..].intersects(ray, distance_min, distance_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use random::seeded_rng;
+
+    fn grey() -> Lambertian {
+        Lambertian::new(Vector4::new3(0.5, 0.5, 0.5))
+    }
+
+    #[test]
+    fn test_rect_hit() {
+        let rect = XZRect::new(-1.0, 1.0, -2.0, 2.0, 0.0, grey());
+
+        let ray = Ray::new(Vector4::new3(0.5, 3.0, 1.0), Vector4::new3(0.0, -1.0, 0.0));
+        let record = rect.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert_eq!(record.distance, 3.0);
+        assert_eq!(record.normal.y, 1.0);
+        assert!(record.front_face);
+        assert_eq!((record.u, record.v), (0.75, 0.75));
+
+        let outside = Ray::new(Vector4::new3(1.5, 3.0, 1.0), Vector4::new3(0.0, -1.0, 0.0));
+        assert!(rect.intersects(&outside, 0.001, f32::MAX, &mut seeded_rng(0)).is_none());
+
+        let parallel = Ray::new(Vector4::new3(0.0, 0.0, -5.0), Vector4::new3(0.0, 0.0, 1.0));
+        assert!(rect.intersects(&parallel, 0.001, f32::MAX, &mut seeded_rng(0)).is_none());
+    }
+
+    #[test]
+    fn test_box_faces_outwards() {
+        let cuboid = AxisAlignedBox::new(Vector4::new3(-1.0, -1.0, -1.0), Vector4::new3(1.0, 1.0, 1.0), grey());
+
+        let directions = [
+            Vector4::new3(1.0, 0.0, 0.0), Vector4::new3(-1.0, 0.0, 0.0),
+            Vector4::new3(0.0, 1.0, 0.0), Vector4::new3(0.0, -1.0, 0.0),
+            Vector4::new3(0.0, 0.0, 1.0), Vector4::new3(0.0, 0.0, -1.0),
+        ];
+
+        for direction in directions.iter() {
+            let ray = Ray::new(*direction * 5.0, -*direction);
+            let record = cuboid.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+            assert_eq!(record.distance, 4.0);
+            assert!(record.front_face);
+            assert_eq!(record.normal.dot3(*direction), 1.0);
+        }
+    }
+}
@@ -1,44 +1,110 @@
+use aabb::Aabb;
 use material::Material;
+use rand::Rng;
 use vector::Vector4;
 use ray::Ray;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct IntersectionRecord {
     pub distance: f32,
     pub intersection_point: Vector4,
+
+    /// The unit surface normal, always facing back against the ray.
     pub normal: Vector4,
-    pub material: Rc<Box<Material>>,
+
+    /// Whether the ray hit the outside of the surface, the side the
+    /// outward normal points to.
+    pub front_face: bool,
+
+    /// The surface coordinates of the hit, used to look up textures.
+    pub u: f32,
+    pub v: f32,
+
+    /// The rate of change of the hit point with `u` and `v`, tangent to the
+    /// surface.
+    pub dpdu: Vector4,
+    pub dpdv: Vector4,
+    pub material: Arc<dyn Material>,
+
+    /// Which object was hit, its position counting from 1 in the outermost
+    /// list of renderables or `Bvh`. 0 if the hit isn't from a list.
+    pub object_id: u32,
 }
 
 impl IntersectionRecord {
-    pub fn new(distance: f32, intersection_point: Vector4, normal: Vector4, material: Rc<Box<Material>>) -> Self {
+    /// Creates the record of `ray` hitting a surface at `distance`. The
+    /// normal is flipped to face the ray if it hit the inside of the surface.
+    pub fn new(ray: &Ray, distance: f32, outward_normal: Vector4, (u, v): (f32, f32),
+               (dpdu, dpdv): (Vector4, Vector4), material: Arc<dyn Material>) -> Self {
+        let front_face = ray.direction().dot3(outward_normal) < 0.0;
+
         IntersectionRecord {
             distance: distance,
-            intersection_point: intersection_point,
-            normal: normal,
+            intersection_point: ray.point_at_distance(distance),
+            normal: if front_face { outward_normal } else { -outward_normal },
+            front_face,
+            u,
+            v,
+            dpdu,
+            dpdv,
             material: material,
+            object_id: 0,
         }
     }
 }
 
-pub trait Renderable {
-    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32) -> Option<IntersectionRecord>;
+pub trait Renderable: Send + Sync {
+    /// The closest hit of `ray` between the two distances. Surfaces ignore
+    /// `rng`, volumes like `ConstantMedium` draw where the ray scatters from
+    /// it and lists pass it on to what they hold.
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord>;
+
+    /// The box enclosing everything that can be hit, or `None` if the
+    /// renderable is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
 }
 
-impl Renderable for [Box<Renderable>] {
-    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32) -> Option<IntersectionRecord> {
+impl Renderable for [Box<dyn Renderable>] {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
         let mut closest_so_far = distance_max;
         let mut record = None;
 
-        for renderable in self.iter() {
-            let intersection = renderable.intersects(ray, distance_min, closest_so_far);
+        for (index, renderable) in self.iter().enumerate() {
+            let intersection = renderable.intersects(ray, distance_min, closest_so_far, rng);
 
-            if let Some(intersection_record) = intersection {
+            if let Some(mut intersection_record) = intersection {
                 closest_so_far = intersection_record.distance;
+                intersection_record.object_id = index as u32 + 1;
                 record = Some(intersection_record);
             }
         }
 
         record
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut surrounding = None;
+
+        for renderable in self.iter() {
+            let bounding_box = renderable.bounding_box()?;
+            surrounding = Some(match surrounding {
+                Some(surrounding) => Aabb::surrounding(&surrounding, &bounding_box),
+                None => bounding_box,
+            });
+        }
+
+        surrounding
+    }
+}
+
+/// Lets one renderable be shared between several places in a scene, e.g.
+/// as the object of many `Transformed` instances.
+impl<TRenderable: Renderable + ?Sized> Renderable for Arc<TRenderable> {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        (**self).intersects(ray, distance_min, distance_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
 }
@@ -0,0 +1,801 @@
+use aov::Aovs;
+use camera::Camera;
+use checkpoint::{ load_checkpoint, save_checkpoint, CheckpointSettings };
+use framebuffer::{ luminance, Framebuffer, Tile };
+use parallel::{ available_threads, map_pixels_parallel, render_parallel };
+use rand::Rng;
+use random::random_in_unit_disk;
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+use sampler::{ Sampler, SamplerRng, SobolSampler };
+use std::io;
+use std::path::Path;
+use std::sync::{ mpsc, Mutex };
+use std::thread;
+use std::time::{ Duration, Instant };
+use vector::Vector4;
+
+/// Rays are started a small distance away from the surface they leave so
+/// that floating point error doesn't make them hit it again.
+const SELF_INTERSECTION_EPSILON: f32 = 0.001;
+
+/// What a ray sees when it escapes the scene.
+#[derive(Clone, Copy)]
+pub enum Background {
+    /// The same colour in every direction.
+    Solid(Vector4),
+
+    /// Linearly blends from `horizon` to `zenith` depending on the y
+    /// component of the ray direction.
+    Gradient { horizon: Vector4, zenith: Vector4 },
+}
+
+impl Background {
+    /// The white to blue sky from the book.
+    pub fn sky() -> Self {
+        Background::Gradient {
+            horizon: Vector4::new3(1.0, 1.0, 1.0),
+            zenith: Vector4::new3(0.5, 0.7, 1.0),
+        }
+    }
+
+    pub fn colour(&self, ray: &Ray) -> Vector4 {
+        match *self {
+            Background::Solid(colour) => colour,
+            Background::Gradient { horizon, zenith } => {
+                // Make unit so (-1.0 < y < 1.0) holds true
+                let unit_direction = ray.direction().unit_vector();
+
+                // Scale to 0.0 < t < 1.0
+                let t = 0.5 * (unit_direction.y + 1.0);
+
+                // Linear interpolation (lerp)
+                //   blended_value = (1 - t) * start_value + t * end_value
+                (horizon * (1.0 - t)) + (zenith * t)
+            }
+        }
+    }
+}
+
+/// Pixels darker than this are judged by their noise relative to this
+/// brightness instead, or black areas would never be considered converged.
+const DARK_LUMINANCE: f32 = 0.01;
+
+/// Settings for spending more samples where the image is noisiest.
+///
+/// Every pixel is given `min_samples`, then passes carry on adding samples
+/// only to the pixels whose noise is still above the threshold, until they
+/// reach `max_samples`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: usize,
+    pub max_samples: usize,
+
+    /// A pixel is done once the standard error of its mean luminance is
+    /// below this fraction of the mean, e.g. `0.01` for 1% noise.
+    pub noise_threshold: f32,
+}
+
+impl AdaptiveSampling {
+    pub fn new(min_samples: usize, max_samples: usize, noise_threshold: f32) -> Self {
+        AdaptiveSampling {
+            min_samples,
+            max_samples,
+            noise_threshold,
+        }
+    }
+
+    /// Whether the pixel at `(x, y)` in image coordinates is smooth enough
+    /// to stop sampling. The noise can't be judged with fewer than two
+    /// samples.
+    pub fn converged(&self, framebuffer: &Framebuffer, x: usize, y: usize) -> bool {
+        let count = framebuffer.sample_count(x, y) as f32;
+        let standard_error = (framebuffer.variance(x, y) / count).sqrt();
+        let mean = luminance(framebuffer.mean(x, y));
+
+        standard_error <= self.noise_threshold * mean.max(DARK_LUMINANCE)
+    }
+}
+
+/// How far a progressive render has got, passed to its callback along with
+/// the framebuffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Progress {
+    /// A tile has had the samples of pass `pass` added to it.
+    Tile { tile: Tile, pass: usize },
+
+    /// Every tile has had the samples of pass `pass` added to it.
+    Pass { pass: usize },
+}
+
+/// The samples of one pixel of a tile being rendered, and how many it
+/// should have by the end of the pass.
+struct PixelSamples {
+    x: usize,
+    y: usize,
+    sum: Vector4,
+    sum_of_squares: f32,
+    count: u32,
+    target: u32,
+}
+
+/// The colour and auxiliary passes of one pixel, before they are split into
+/// separate buffers.
+struct PixelAovs {
+    colour: Vector4,
+    depth: f32,
+    normal: Vector4,
+    albedo: Vector4,
+    object_id: u32,
+}
+
+/// Renders a scene through a camera into an RGB framebuffer.
+///
+/// The settings are public fields, `new` fills them in with the values used
+/// throughout the book.
+pub struct Renderer {
+    pub camera: Camera,
+    pub width: usize,
+    pub height: usize,
+
+    /// With no samples every pixel is black, the same in every render.
+    pub samples_per_pixel: usize,
+
+    /// The deepest bounce at which a ray may still scatter, counting the
+    /// first surface it hits as 0. Past it the ray is considered to have
+    /// been absorbed.
+    pub max_depth: u32,
+    pub background: Background,
+
+    /// Each channel is raised to `1 / gamma` before being written into the
+    /// framebuffer. A gamma of `1.0` leaves the linear values untouched.
+    pub gamma: f32,
+    pub threads: usize,
+
+    /// The size of the square tiles a progressive render is divided into.
+    pub tile_size: usize,
+
+    /// How many samples a progressive render adds to each pixel per pass.
+    pub samples_per_pass: usize,
+
+    /// When set, `render` and the progressive renders stop sampling pixels
+    /// once they are smooth and `samples_per_pixel` is ignored. The AOV
+    /// passes always take `samples_per_pixel`.
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+
+    /// Generates the numbers each sample is built from, `new` uses a
+    /// `SobolSampler`.
+    pub sampler: Box<dyn Sampler>,
+
+    /// Renders with the same seed produce exactly the same image, whatever
+    /// the number of threads.
+    pub seed: u32,
+}
+
+impl Renderer {
+    pub fn new(camera: Camera, width: usize, height: usize) -> Self {
+        Renderer {
+            camera,
+            width,
+            height,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            background: Background::sky(),
+            gamma: 2.0,
+            threads: available_threads(),
+            tile_size: 32,
+            samples_per_pass: 4,
+            adaptive_sampling: None,
+            sampler: Box::new(SobolSampler),
+            seed: 0,
+        }
+    }
+
+    /// Renders `scene`, returning `width * height` RGB triples starting at
+    /// the top left of the image.
+    pub fn render<TScene: Renderable + ?Sized>(&self, scene: &TScene) -> Vec<f32> {
+        if self.adaptive_sampling.is_some() {
+            // Deciding when to stop needs the samples so far in every pixel
+            let mut framebuffer = Framebuffer::new(self.width, self.height);
+            self.render_progressive(scene, &mut framebuffer, |_, _| {});
+            return framebuffer.image(self.gamma);
+        }
+
+        render_parallel(self.width, self.height, self.threads, |x, y| {
+            let mut anti_aliased_colour = Vector4::new3(0.0, 0.0, 0.0);
+            for sample in 0..self.samples_per_pixel {
+                anti_aliased_colour += self.sample(x, y, sample, scene);
+            }
+
+            gamma_correct(anti_aliased_colour / (self.samples_per_pixel.max(1) as f32), self.gamma)
+        })
+    }
+
+    /// Renders `scene` into `framebuffer` a few samples per pixel at a time,
+    /// calling `callback` with the partial image after each tile and each
+    /// pass, so the image can be shown as it converges.
+    ///
+    /// Each pass adds `samples_per_pass` samples to every pixel, until they
+    /// all have `samples_per_pixel`, or with adaptive sampling until they
+    /// are all smooth or at the maximum. Samples already in the framebuffer
+    /// are kept, and the finished image is exactly the same as `render`
+    /// would produce. The callback runs on the calling thread while the workers
+    /// carry on.
+    ///
+    /// Panics if the framebuffer isn't the same size as the renderer.
+    pub fn render_progressive<TScene, TCallback>(&self, scene: &TScene, framebuffer: &mut Framebuffer, mut callback: TCallback)
+        where TScene: Renderable + ?Sized, TCallback: FnMut(&Framebuffer, Progress) {
+
+        assert!(framebuffer.width() == self.width && framebuffer.height() == self.height,
+                "the framebuffer is {}x{} but the renderer is {}x{}",
+                framebuffer.width(), framebuffer.height(), self.width, self.height);
+
+        let mut pass = 0;
+        loop {
+            // Each tile starts from the samples already in its pixels
+            let work: Vec<(Tile, Vec<PixelSamples>)> = framebuffer.tiles(self.tile_size).into_iter()
+                .map(|tile| {
+                    let pixels: Vec<PixelSamples> = tile_pixels(&tile).map(|(x, y)| PixelSamples {
+                        x,
+                        y,
+                        sum: framebuffer.sum(x, y),
+                        sum_of_squares: framebuffer.sum_of_squares(x, y),
+                        count: framebuffer.sample_count(x, y),
+                        target: self.pass_target(framebuffer, x, y),
+                    }).collect();
+                    (tile, pixels)
+                })
+                .filter(|(_, pixels)| pixels.iter().any(|pixel| pixel.target > pixel.count))
+                .collect();
+
+            if work.is_empty() {
+                break;
+            }
+
+            {
+                let queue = Mutex::new(work.into_iter());
+                let (sender, receiver) = mpsc::channel();
+                let queue = &queue;
+
+                thread::scope(|scope| {
+                    for _ in 0..self.threads.max(1) {
+                        let sender = sender.clone();
+                        scope.spawn(move || {
+                            loop {
+                                let (tile, mut pixels) = match queue.lock().unwrap().next() {
+                                    Some(work) => work,
+                                    None => break,
+                                };
+
+                                for pixel in pixels.iter_mut() {
+                                    for sample in pixel.count..pixel.target {
+                                        let colour = self.sample(pixel.x, self.height - 1 - pixel.y, sample as usize, scene);
+                                        pixel.sum += colour;
+                                        pixel.sum_of_squares += luminance(colour) * luminance(colour);
+                                    }
+                                    pixel.count = pixel.count.max(pixel.target);
+                                }
+
+                                sender.send((tile, pixels)).unwrap();
+                            }
+                        });
+                    }
+
+                    // The workers hold the only senders left, so this ends
+                    // when they finish. Only this thread writes to the
+                    // framebuffer, so the workers never wait on the callback.
+                    drop(sender);
+                    for (tile, pixels) in receiver.iter() {
+                        for pixel in pixels {
+                            framebuffer.set(pixel.x, pixel.y, pixel.sum, pixel.sum_of_squares, pixel.count);
+                        }
+                        callback(framebuffer, Progress::Tile { tile, pass });
+                    }
+                });
+            }
+
+            callback(framebuffer, Progress::Pass { pass });
+            pass += 1;
+        }
+    }
+
+    /// Renders `scene` like `render`, along with depth, normal, albedo and
+    /// object ID passes taken from where each camera ray first hits. The
+    /// image is exactly the same as `render` would produce.
+    pub fn render_with_aovs<TScene: Renderable + ?Sized>(&self, scene: &TScene) -> (Vec<f32>, Aovs) {
+        let pixels = map_pixels_parallel(self.width, self.height, self.threads, |x, y| {
+            let zero = Vector4::new3(0.0, 0.0, 0.0);
+            let (mut colour, mut normal, mut albedo) = (zero, zero, zero);
+            let (mut depth, mut hits) = (0.0, 0);
+            let mut object_ids: Vec<(u32, usize)> = Vec::new();
+
+            for sample in 0..self.samples_per_pixel {
+                let (ray, mut rng) = self.camera_ray(x, y, sample);
+
+                let object_id = match self.intersect(&ray, scene, 0, &mut rng) {
+                    Some(intersection) => {
+                        depth += intersection.distance * ray.direction().length();
+                        hits += 1;
+                        normal += intersection.normal;
+                        albedo += intersection.material.albedo(&intersection);
+                        colour += self.shade(&ray, &intersection, scene, 0, &mut rng);
+                        intersection.object_id
+                    }
+                    None => {
+                        let background = self.background.colour(&ray);
+                        albedo += background;
+                        colour += background;
+                        0
+                    }
+                };
+
+                match object_ids.iter_mut().find(|&&mut (id, _)| id == object_id) {
+                    Some(&mut (_, ref mut count)) => *count += 1,
+                    None => object_ids.push((object_id, 1)),
+                }
+            }
+
+            let samples = self.samples_per_pixel.max(1) as f32;
+
+            // The most common object, the earliest seen wins a tie
+            let object_id = object_ids.iter()
+                .fold((0, 0), |best, &(id, count)| if count > best.1 { (id, count) } else { best })
+                .0;
+
+            PixelAovs {
+                colour: gamma_correct(colour / samples, self.gamma),
+                depth: if hits > 0 { depth / hits as f32 } else { f32::INFINITY },
+                normal: if normal.length_squared() > 0.0 { normal.unit_vector() } else { normal },
+                albedo: albedo / samples,
+                object_id,
+            }
+        });
+
+        let triples = |channel: &dyn Fn(&PixelAovs) -> Vector4| -> Vec<f32> {
+            pixels.iter().flat_map(|pixel| {
+                let value = channel(pixel);
+                [value.x, value.y, value.z]
+            }).collect()
+        };
+
+        let image = triples(&|pixel| pixel.colour);
+        let aovs = Aovs {
+            width: self.width,
+            height: self.height,
+            depth: pixels.iter().map(|pixel| pixel.depth).collect(),
+            normal: triples(&|pixel| pixel.normal),
+            albedo: triples(&|pixel| pixel.albedo),
+            object_id: pixels.iter().map(|pixel| pixel.object_id).collect(),
+        };
+
+        (image, aovs)
+    }
+
+    /// How many samples the pixel at `(x, y)` in image coordinates should
+    /// have at the end of the current pass, its current count if it's done.
+    fn pass_target(&self, framebuffer: &Framebuffer, x: usize, y: usize) -> u32 {
+        let count = framebuffer.sample_count(x, y);
+        let samples_per_pass = self.samples_per_pass.max(1) as u32;
+
+        let limit = match self.adaptive_sampling {
+            Some(ref adaptive) => {
+                let min_samples = adaptive.min_samples.max(2) as u32;
+                if count >= min_samples && adaptive.converged(framebuffer, x, y) {
+                    return count;
+                }
+
+                adaptive.max_samples.max(adaptive.min_samples) as u32
+            }
+            None => self.samples_per_pixel as u32,
+        };
+
+        ((count / samples_per_pass + 1) * samples_per_pass).min(limit).max(count)
+    }
+
+    /// Renders progressively like `render_progressive`, saving a checkpoint
+    /// to `path` whenever `interval` has passed since the last one, and
+    /// again once finished.
+    ///
+    /// If `path` already holds a checkpoint the render carries on from it,
+    /// and the image is exactly the same as if it had never stopped. Raising
+    /// `samples_per_pixel` and resuming from a finished render adds more
+    /// samples to it. The checkpoint must have been made with the same size,
+    /// seed, sampler, `max_depth` and `samples_per_pass`.
+    pub fn render_resumable<TScene, TPath, TCallback>(&self, scene: &TScene, path: TPath, interval: Duration,
+                                                      mut callback: TCallback) -> io::Result<Framebuffer>
+        where TScene: Renderable + ?Sized, TPath: AsRef<Path>, TCallback: FnMut(&Framebuffer, Progress) {
+
+        let path = path.as_ref();
+
+        let mut framebuffer = if path.exists() {
+            let checkpoint = load_checkpoint(path)?;
+            let framebuffer = checkpoint.framebuffer;
+            let settings = self.checkpoint_settings();
+
+            if framebuffer.width() != self.width || framebuffer.height() != self.height {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("the checkpoint is {}x{} but the renderer is {}x{}",
+                            framebuffer.width(), framebuffer.height(), self.width, self.height)));
+            }
+
+            if checkpoint.settings != settings {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("the checkpoint was rendered with {} but the renderer has {}",
+                            checkpoint.settings, settings)));
+            }
+
+            framebuffer
+        } else {
+            Framebuffer::new(self.width, self.height)
+        };
+
+        let settings = self.checkpoint_settings();
+        let mut last_saved = Instant::now();
+        let mut save_error = None;
+
+        self.render_progressive(scene, &mut framebuffer, |framebuffer, progress| {
+            callback(framebuffer, progress);
+
+            if save_error.is_none() && last_saved.elapsed() >= interval {
+                save_error = save_checkpoint(path, framebuffer, &settings).err();
+                last_saved = Instant::now();
+            }
+        });
+
+        if let Some(error) = save_error {
+            return Err(error);
+        }
+
+        save_checkpoint(path, &framebuffer, &settings)?;
+        Ok(framebuffer)
+    }
+
+    fn checkpoint_settings(&self) -> CheckpointSettings {
+        CheckpointSettings {
+            seed: self.seed,
+            sampler: self.sampler.name(),
+            max_depth: self.max_depth,
+            samples_per_pass: self.samples_per_pass as u32,
+        }
+    }
+
+    /// The colour seen by one sample of the pixel at screen coordinates
+    /// `(x, y)`, the same every time for the same seed.
+    fn sample<TScene: Renderable + ?Sized>(&self, x: usize, y: usize, sample: usize, scene: &TScene) -> Vector4 {
+        let (ray, mut rng) = self.camera_ray(x, y, sample);
+        self.colour(&ray, scene, 0, &mut rng)
+    }
+
+    /// Casts the ray for one sample of a pixel, returning it with the
+    /// generator the rest of the sample should use.
+    fn camera_ray(&self, x: usize, y: usize, sample: usize) -> (Ray, SamplerRng<'_>) {
+        let mut rng = SamplerRng::new(&*self.sampler, self.seed, x, y, sample);
+        let u = (x as f32 + rng.next_f32()) / self.width as f32;
+        let v = (y as f32 + rng.next_f32()) / self.height as f32;
+
+        rng.start_lens();
+        let lens = random_in_unit_disk(&mut rng);
+        rng.start_time();
+        let time = rng.next_f32();
+
+        (self.camera.ray_through(u, v, lens, time), rng)
+    }
+
+    /// Follows `ray` through the scene, returning the light it carries back.
+    /// `rng` supplies the numbers for each bounce after `depth` earlier ones.
+    pub fn colour<TScene: Renderable + ?Sized>(&self, ray: &Ray, scene: &TScene, depth: u32, rng: &mut SamplerRng) -> Vector4 {
+        match self.intersect(ray, scene, depth, rng) {
+            Some(intersection) => self.shade(ray, &intersection, scene, depth, rng),
+            None => self.background.colour(ray),
+        }
+    }
+
+    /// The closest hit of the ray cast after `depth` bounces.
+    fn intersect<TScene: Renderable + ?Sized>(&self, ray: &Ray, scene: &TScene, depth: u32,
+                                              rng: &mut SamplerRng) -> Option<IntersectionRecord> {
+        rng.start_ray(depth);
+        scene.intersects(ray, SELF_INTERSECTION_EPSILON, f32::MAX, rng)
+    }
+
+    /// The light leaving a surface back along `ray`, emitted by it and
+    /// scattered off it.
+    fn shade<TScene: Renderable + ?Sized>(&self, ray: &Ray, intersection: &IntersectionRecord, scene: &TScene,
+                                          depth: u32, rng: &mut SamplerRng) -> Vector4 {
+        let emitted = intersection.material.emitted(intersection);
+
+        if depth <= self.max_depth {
+            rng.start_bounce(depth);
+            if let Some((scattered, attenuation)) = intersection.material.scatter(ray, intersection, rng) {
+                return emitted + attenuation * self.colour(&scattered, scene, depth + 1, rng);
+            }
+        }
+
+        emitted
+    }
+}
+
+/// Raises each channel of `colour` to `1 / gamma`, a gamma of `1.0` leaves
+/// it untouched.
+pub(crate) fn gamma_correct(colour: Vector4, gamma: f32) -> Vector4 {
+    if gamma == 1.0 {
+        return colour;
+    }
+
+    let exponent = 1.0 / gamma;
+    Vector4::new3(colour.x.powf(exponent), colour.y.powf(exponent), colour.z.powf(exponent))
+}
+
+/// Every pixel in a tile, in image coordinates.
+fn tile_pixels(tile: &Tile) -> impl Iterator<Item = (usize, usize)> {
+    let tile = *tile;
+    (tile.y..tile.y + tile.height).flat_map(move |y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::{ Dielectric, DiffuseLight, Lambertian, Metal };
+    use sampler::{ HaltonSampler, IndependentSampler, StratifiedSampler };
+    use sphere::Sphere;
+
+    fn test_renderer(threads: usize, seed: u32) -> Renderer {
+        let camera = Camera::new(Vector4::new3(0.0, 1.0, 3.0), Vector4::new3(0.0, 0.0, -1.0),
+                                 Vector4::new3(0.0, 1.0, 0.0), 60.0, 2.0, 0.1, 4.0);
+
+        let mut renderer = Renderer::new(camera, 16, 8);
+        renderer.samples_per_pixel = 4;
+        renderer.threads = threads;
+        renderer.seed = seed;
+        renderer
+    }
+
+    fn test_scene() -> Vec<Box<dyn Renderable>> {
+        vec![
+            Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, Lambertian::new(Vector4::new3(0.8, 0.8, 0.0)))),
+            Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, Lambertian::new(Vector4::new3(0.8, 0.3, 0.3)))),
+            Box::new(Sphere::new(Vector4::new3(1.0, 0.0, -1.0), 0.5, Metal::new(Vector4::new3(0.8, 0.6, 0.2), 0.3))),
+            Box::new(Sphere::new(Vector4::new3(-1.0, 0.0, -1.0), 0.5, Dielectric::new(1.5))),
+        ]
+    }
+
+    #[test]
+    fn test_render_is_reproducible() {
+        let scene = test_scene();
+
+        let single_threaded = test_renderer(1, 7).render(&scene[..]);
+        let multi_threaded = test_renderer(3, 7).render(&scene[..]);
+
+        assert_eq!(single_threaded.len(), 16 * 8 * 3);
+        assert!(single_threaded.iter().zip(multi_threaded.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
+
+    #[test]
+    fn test_every_sampler_is_reproducible() {
+        let scene = test_scene();
+        let samplers: Vec<fn() -> Box<dyn Sampler>> = vec![
+            || Box::new(IndependentSampler),
+            || Box::new(StratifiedSampler::new(2)),
+            || Box::new(HaltonSampler),
+            || Box::new(SobolSampler),
+        ];
+
+        for sampler in samplers {
+            let mut single_threaded = test_renderer(1, 7);
+            single_threaded.sampler = sampler();
+            let mut multi_threaded = test_renderer(3, 7);
+            multi_threaded.sampler = sampler();
+
+            let a = single_threaded.render(&scene[..]);
+            let b = multi_threaded.render(&scene[..]);
+            assert!(a.iter().zip(b.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+        }
+    }
+
+    #[test]
+    fn test_render_depends_on_seed() {
+        let scene = test_scene();
+
+        let a = test_renderer(1, 1).render(&scene[..]);
+        let b = test_renderer(1, 2).render(&scene[..]);
+
+        assert!(a != b);
+    }
+
+    #[test]
+    fn test_emission() {
+        let light: Vec<Box<dyn Renderable>> = vec![
+            Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 100.0, DiffuseLight::new(Vector4::new3(4.0, 2.0, 1.0)))),
+        ];
+
+        let mut renderer = test_renderer(1, 0);
+        renderer.background = Background::Solid(Vector4::new3(0.0, 0.0, 0.0));
+        renderer.gamma = 1.0;
+
+        let image = renderer.render(&light[..]);
+        assert!(image.chunks(3).all(|pixel| pixel == [4.0, 2.0, 1.0]));
+    }
+
+    #[test]
+    fn test_aovs() {
+        let scene: Vec<Box<dyn Renderable>> = vec![
+            Box::new(Sphere::new(Vector4::new3(0.0, 0.0, -1.0), 0.5, Lambertian::new(Vector4::new3(0.8, 0.3, 0.3)))),
+            Box::new(Sphere::new(Vector4::new3(0.0, -100.5, -1.0), 100.0, Lambertian::new(Vector4::new3(0.8, 0.8, 0.0)))),
+        ];
+
+        // Big enough that the middle pixel is covered by the small sphere
+        let mut renderer = test_renderer(1, 3);
+        renderer.width = 32;
+        renderer.height = 16;
+        let (image, aovs) = renderer.render_with_aovs(&scene[..]);
+
+        // The image matches a plain render exactly
+        let plain = renderer.render(&scene[..]);
+        assert!(image.iter().zip(plain.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+        assert_eq!(aovs.depth.len(), 32 * 16);
+        assert_eq!(aovs.normal.len(), 32 * 16 * 3);
+
+        // The top left looks up into the sky, the bottom row at the ground
+        assert_eq!(aovs.object_id[0], 0);
+        assert!(aovs.depth[0].is_infinite());
+        assert_eq!(&aovs.normal[0..3], &[0.0, 0.0, 0.0]);
+
+        let bottom = 32 * 15;
+        assert_eq!(aovs.object_id[bottom], 2);
+        assert!(aovs.depth[bottom].is_finite());
+        assert!(aovs.normal[bottom * 3 + 1] > 0.9);
+        assert!((aovs.albedo[bottom * 3 + 2]).abs() < 1e-6);
+
+        // The small sphere is in the middle of the picture
+        let centre = 32 * 8 + 16;
+        assert_eq!(aovs.object_id[centre], 1);
+        assert!((aovs.albedo[centre * 3] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_progressive_matches_render() {
+        let scene = test_scene();
+
+        let mut renderer = test_renderer(2, 5);
+        renderer.tile_size = 5;
+        renderer.samples_per_pass = 3;
+
+        let mut framebuffer = Framebuffer::new(16, 8);
+        let mut tiles = 0;
+        let mut passes = Vec::new();
+        renderer.render_progressive(&scene[..], &mut framebuffer, |framebuffer, progress| {
+            match progress {
+                Progress::Tile { .. } => tiles += 1,
+                Progress::Pass { pass } => passes.push((pass, framebuffer.sample_count(0, 0))),
+            }
+        });
+
+        // 4 samples, 3 in the first pass and 1 in the second, over 4x2 tiles
+        assert_eq!(passes, vec![(0, 3), (1, 4)]);
+        assert_eq!(tiles, 2 * 4 * 2);
+
+        let image = framebuffer.image(renderer.gamma);
+        let expected = renderer.render(&scene[..]);
+        assert!(image.iter().zip(expected.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
+
+    #[test]
+    fn test_no_samples_is_black() {
+        let scene = test_scene();
+
+        let mut renderer = test_renderer(2, 5);
+        renderer.samples_per_pixel = 0;
+
+        let mut framebuffer = Framebuffer::new(16, 8);
+        renderer.render_progressive(&scene[..], &mut framebuffer, |_, _| {});
+
+        let (image, _) = renderer.render_with_aovs(&scene[..]);
+        assert!(image.iter().all(|&value| value == 0.0));
+        assert!(renderer.render(&scene[..]).iter().all(|&value| value == 0.0));
+        assert!(framebuffer.image(renderer.gamma).iter().all(|&value| value == 0.0));
+    }
+
+    #[test]
+    fn test_resume_matches_uninterrupted_render() {
+        let scene = test_scene();
+        let path = ::std::env::temp_dir().join(format!("weekendraytracer-resume-{}.ckpt", ::std::process::id()));
+        let _ = ::std::fs::remove_file(&path);
+
+        // Stop after a couple of samples, then carry on to the full count
+        let mut renderer = test_renderer(2, 9);
+        renderer.samples_per_pixel = 2;
+        renderer.samples_per_pass = 2;
+        renderer.render_resumable(&scene[..], &path, Duration::from_secs(0), |_, _| {}).unwrap();
+
+        renderer.samples_per_pixel = 5;
+        let framebuffer = renderer.render_resumable(&scene[..], &path, Duration::from_secs(3600), |_, _| {}).unwrap();
+
+        let expected = renderer.render(&scene[..]);
+        let image = framebuffer.image(renderer.gamma);
+        assert!(image.iter().zip(expected.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+        // The finished render was saved, and won't resume with any of the
+        // settings changed
+        assert_eq!(load_checkpoint(&path).unwrap().framebuffer.sample_count(3, 3), 5);
+        let changes: Vec<fn(&mut Renderer)> = vec![
+            |renderer| renderer.seed = 10,
+            |renderer| renderer.sampler = Box::new(StratifiedSampler::new(2)),
+            |renderer| renderer.max_depth = 3,
+            |renderer| renderer.samples_per_pass = 4,
+        ];
+        for change in changes {
+            let mut changed = test_renderer(2, 9);
+            changed.samples_per_pixel = 5;
+            changed.samples_per_pass = 2;
+            change(&mut changed);
+            assert!(changed.render_resumable(&scene[..], &path, Duration::from_secs(3600), |_, _| {}).is_err());
+        }
+
+        ::std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_after_interruption() {
+        let scene = test_scene();
+        let path = ::std::env::temp_dir().join(format!("weekendraytracer-interrupt-{}.ckpt", ::std::process::id()));
+        let _ = ::std::fs::remove_file(&path);
+
+        let mut renderer = test_renderer(2, 11);
+        renderer.tile_size = 4;
+        renderer.samples_per_pass = 3;
+
+        // Die part way through the first pass, checkpointing after every tile
+        let mut tiles = 0;
+        let interrupted = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+            renderer.render_resumable(&scene[..], &path, Duration::from_secs(0), |_, _| {
+                tiles += 1;
+                if tiles == 3 {
+                    panic!("preempted");
+                }
+            })
+        }));
+        assert!(interrupted.is_err());
+
+        // The workers may have finished more tiles by the time it was saved,
+        // but never got past the first pass
+        let partial = load_checkpoint(&path).unwrap().framebuffer;
+        let counts: Vec<u32> = (0..8).flat_map(|y| (0..16).map(move |x| (x, y))).map(|(x, y)| partial.sample_count(x, y)).collect();
+        assert!(counts.iter().all(|&count| count == 0 || count == 3));
+        assert!(counts.contains(&3));
+
+        let framebuffer = renderer.render_resumable(&scene[..], &path, Duration::from_secs(3600), |_, _| {}).unwrap();
+        let expected = renderer.render(&scene[..]);
+        let image = framebuffer.image(renderer.gamma);
+        assert!(image.iter().zip(expected.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+
+        ::std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_adaptive_sampling() {
+        let scene = test_scene();
+
+        let mut renderer = test_renderer(2, 4);
+        renderer.samples_per_pass = 4;
+        renderer.adaptive_sampling = Some(AdaptiveSampling::new(8, 64, 0.02));
+
+        let mut framebuffer = Framebuffer::new(16, 8);
+        renderer.render_progressive(&scene[..], &mut framebuffer, |_, _| {});
+
+        let counts: Vec<u32> = (0..8).flat_map(|y| (0..16).map(move |x| (x, y))).map(|(x, y)| framebuffer.sample_count(x, y)).collect();
+        assert!(counts.iter().all(|&count| (8..=64).contains(&count)));
+
+        // The smooth sky at the top stops early, the noisy spheres don't
+        assert_eq!(framebuffer.sample_count(0, 0), 8);
+        assert!(counts.iter().any(|&count| count > 8));
+
+        // Every pixel finished is either smooth or out of samples
+        let adaptive = renderer.adaptive_sampling.unwrap();
+        assert!((0..8).all(|y| (0..16).all(|x| framebuffer.sample_count(x, y) == 64 || adaptive.converged(&framebuffer, x, y))));
+
+        // A plain render stops in the same places
+        let image = renderer.render(&scene[..]);
+        let expected = framebuffer.image(renderer.gamma);
+        assert!(image.iter().zip(expected.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
+}
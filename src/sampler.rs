@@ -0,0 +1,498 @@
+use rand::Rng;
+use random::mix;
+
+/// The first of the two dimensions which place a sample within its pixel.
+pub const PIXEL_DIMENSION: usize = 0;
+
+/// The first of the two dimensions which place a sample on the camera lens.
+pub const LENS_DIMENSION: usize = 2;
+
+/// The dimension which picks the time a sample's ray is cast at.
+pub const TIME_DIMENSION: usize = 4;
+
+/// The first dimension given to the camera ray once it has been cast,
+/// each bounce after it gets the next `BOUNCE_DIMENSIONS`.
+pub const FIRST_BOUNCE_DIMENSION: usize = 5;
+
+/// How many dimensions each bounce gets. The first picks where the ray
+/// scatters in any medium it passes through, the rest are enough for the
+/// material it hits to pick a direction in the unit sphere.
+pub const BOUNCE_DIMENSIONS: usize = 4;
+
+// The largest f32 below 1.0
+const ONE_MINUS_EPSILON: f32 = 1.0 - f32::EPSILON / 2.0;
+
+// Numbers drawn past a bounce's dimensions are hashed with this mixed in,
+// so they don't repeat the values of the next bounce
+const OVERFLOW_STREAM: u32 = 0x6f76_6572;
+
+/// Generates the numbers each sample of a pixel is built from.
+///
+/// A sample is a point in many dimensions: where it lands in the pixel,
+/// where on the lens, when, and which way it scatters at each bounce. The
+/// samplers other than `IndependentSampler` spread the samples of a pixel
+/// out evenly in each of those dimensions, so images converge with fewer
+/// samples than they would with independent random numbers.
+pub trait Sampler: Send + Sync {
+    /// Dimension `dimension` of sample `index` of the pixel at `(x, y)`, from
+    /// 0.0 up to 1.0. The same arguments always give the same number.
+    fn get(&self, seed: u32, x: usize, y: usize, index: usize, dimension: usize) -> f32;
+
+    /// Identifies the sampler and its settings, so a checkpoint is only
+    /// resumed with the sampler that started it.
+    fn name(&self) -> String;
+}
+
+/// Independent random numbers for every dimension of every sample.
+#[derive(Clone, Copy, Debug)]
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn get(&self, seed: u32, x: usize, y: usize, index: usize, dimension: usize) -> f32 {
+        to_unit(hash(seed, x, y, index, dimension))
+    }
+
+    fn name(&self) -> String {
+        "independent".to_string()
+    }
+}
+
+/// Divides each pair of dimensions into a `strata` by `strata` grid and
+/// places one sample at a random point in each cell.
+///
+/// The cells are visited in a different order for every pixel and pair of
+/// dimensions, so that the dimensions aren't correlated. The samples are
+/// only spread out evenly in groups of `strata * strata`, so
+/// `samples_per_pixel` should be a multiple of it.
+#[derive(Clone, Copy, Debug)]
+pub struct StratifiedSampler {
+    strata: usize,
+}
+
+impl StratifiedSampler {
+    pub fn new(strata: usize) -> Self {
+        assert!(strata > 0, "a stratified sampler needs at least one stratum");
+
+        StratifiedSampler {
+            strata,
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn get(&self, seed: u32, x: usize, y: usize, index: usize, dimension: usize) -> f32 {
+        let cells = self.strata * self.strata;
+        let pair = dimension / 2;
+
+        // Each group of `cells` samples visits every cell once
+        let order = hash(seed, x, y, index / cells, pair);
+        let cell = permute((index % cells) as u32, cells as u32, order) as usize;
+        let column = if dimension.is_multiple_of(2) { cell % self.strata } else { cell / self.strata };
+
+        let jitter = to_unit(hash(seed, x, y, index, dimension));
+        ((column as f32 + jitter) / self.strata as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn name(&self) -> String {
+        format!("stratified {}", self.strata)
+    }
+}
+
+/// The Halton sequence, with a random offset for each pixel.
+///
+/// Each dimension is the radical inverse of the sample index in the next
+/// prime base. Only the first `HALTON_PRIMES.len()` dimensions are covered,
+/// past that the samples are independent random numbers.
+#[derive(Clone, Copy, Debug)]
+pub struct HaltonSampler;
+
+const HALTON_PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+impl Sampler for HaltonSampler {
+    fn get(&self, seed: u32, x: usize, y: usize, index: usize, dimension: usize) -> f32 {
+        let base = match HALTON_PRIMES.get(dimension) {
+            Some(&base) => base,
+            None => return IndependentSampler.get(seed, x, y, index, dimension),
+        };
+
+        // Shifting every sample of a pixel by the same amount keeps them
+        // spread out, but stops neighbouring pixels from matching
+        let offset = to_unit(hash(seed, x, y, 0, dimension)) as f64;
+        let value = (radical_inverse(base, index as u64) + offset).fract();
+        (value as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn name(&self) -> String {
+        "halton".to_string()
+    }
+}
+
+/// The Sobol sequence with Owen scrambling, following Burley's "Practical
+/// Hash-based Owen Scrambling".
+///
+/// The dimensions are taken four at a time from the first four Sobol
+/// dimensions, each group shuffling the order of the samples differently so
+/// that the groups aren't correlated. Any power of two number of samples is
+/// spread out evenly.
+#[derive(Clone, Copy, Debug)]
+pub struct SobolSampler;
+
+impl Sampler for SobolSampler {
+    fn get(&self, seed: u32, x: usize, y: usize, index: usize, dimension: usize) -> f32 {
+        let group = dimension / 4;
+        let component = dimension % 4;
+
+        let shuffle = hash(seed, x, y, 0, group);
+        let index = nested_uniform_scramble(index as u32, shuffle);
+
+        let scramble = hash(seed, x, y, 1 + component, group);
+        to_unit(nested_uniform_scramble(sobol(index, component), scramble))
+    }
+
+    fn name(&self) -> String {
+        "sobol".to_string()
+    }
+}
+
+/// A generator which draws its numbers from a `Sampler`, one dimension after
+/// another, so that it can be passed to anything which takes an `Rng`.
+///
+/// It starts at the pixel's dimensions, `start_lens` and `start_time` move it
+/// on to the rest of the camera's, and `start_ray` and `start_bounce` to the
+/// dimensions of a bounce. Any numbers drawn past those are independent
+/// random numbers, so that each part of a sample always starts at the same
+/// dimension.
+pub struct SamplerRng<'a> {
+    sampler: &'a dyn Sampler,
+    seed: u32,
+    x: usize,
+    y: usize,
+    index: usize,
+    dimension: usize,
+    end: usize,
+}
+
+impl<'a> SamplerRng<'a> {
+    pub fn new(sampler: &'a dyn Sampler, seed: u32, x: usize, y: usize, index: usize) -> Self {
+        SamplerRng {
+            sampler,
+            seed,
+            x,
+            y,
+            index,
+            dimension: PIXEL_DIMENSION,
+            end: LENS_DIMENSION,
+        }
+    }
+
+    /// Moves on to the dimensions for placing the sample on the camera lens.
+    pub fn start_lens(&mut self) {
+        self.dimension = LENS_DIMENSION;
+        self.end = TIME_DIMENSION;
+    }
+
+    /// Moves on to the dimension for picking the time the ray is cast at.
+    pub fn start_time(&mut self) {
+        self.dimension = TIME_DIMENSION;
+        self.end = FIRST_BOUNCE_DIMENSION;
+    }
+
+    /// Moves on to the dimension for tracing the ray cast after `depth`
+    /// bounces, which media use to pick where it scatters.
+    pub fn start_ray(&mut self, depth: u32) {
+        self.dimension = FIRST_BOUNCE_DIMENSION + depth as usize * BOUNCE_DIMENSIONS;
+        self.end = self.dimension + 1;
+    }
+
+    /// Moves on to the dimensions for scattering off the surface reached
+    /// after `depth` earlier bounces.
+    pub fn start_bounce(&mut self, depth: u32) {
+        self.dimension = FIRST_BOUNCE_DIMENSION + depth as usize * BOUNCE_DIMENSIONS + 1;
+        self.end = self.dimension + BOUNCE_DIMENSIONS - 1;
+    }
+}
+
+impl<'a> Rng for SamplerRng<'a> {
+    fn next_u32(&mut self) -> u32 {
+        // A sampler's numbers only have the 24 bits of an f32, the low 8
+        // come from a hash so that small ranges like `% 2` still vary
+        let low = hash(self.seed ^ OVERFLOW_STREAM, self.x, self.y, self.index, self.dimension) & 0xff;
+        let high = ((self.next_f32() * (1u32 << 24) as f32) as u32).min((1 << 24) - 1);
+
+        high << 8 | low
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        let value = if self.dimension < self.end {
+            self.sampler.get(self.seed, self.x, self.y, self.index, self.dimension)
+        } else {
+            IndependentSampler.get(self.seed ^ OVERFLOW_STREAM, self.x, self.y, self.index, self.dimension)
+        };
+
+        self.dimension += 1;
+        value
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.next_f32() as f64
+    }
+}
+
+fn hash(seed: u32, x: usize, y: usize, index: usize, dimension: usize) -> u32 {
+    let mut state = mix(seed as u64);
+    state = mix(state ^ x as u64);
+    state = mix(state ^ y as u64);
+    state = mix(state ^ index as u64);
+    state = mix(state ^ dimension as u64);
+    (state >> 32) as u32
+}
+
+// The top 24 bits fill an f32's mantissa exactly
+fn to_unit(bits: u32) -> f32 {
+    (bits >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn radical_inverse(base: u64, mut index: u64) -> f64 {
+    let mut reversed = 0;
+    let mut scale = 1.0;
+
+    while index > 0 {
+        reversed = reversed * base + index % base;
+        scale /= base as f64;
+        index /= base;
+    }
+
+    reversed as f64 * scale
+}
+
+// Kensler's hash based permutation from "Correlated Multi-Jittered
+// Sampling", the position of `index` in a shuffle of 0..length
+fn permute(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    // Shuffles within the next power of two, until landing inside 0..length
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= mask;
+        index ^= index >> 5;
+
+        if index < length {
+            return (index + seed) % length;
+        }
+    }
+}
+
+// The direction numbers of the first four Sobol dimensions, from Joe and
+// Kuo's primitive polynomials and initial numbers
+const SOBOL_DIRECTIONS: [[u32; 32]; 4] = [
+    sobol_directions(0, 0, [0, 0, 0]),
+    sobol_directions(1, 0, [1, 0, 0]),
+    sobol_directions(2, 1, [1, 3, 0]),
+    sobol_directions(3, 1, [1, 3, 1]),
+];
+
+const fn sobol_directions(degree: usize, coefficients: u32, initial: [u32; 3]) -> [u32; 32] {
+    let mut directions = [0; 32];
+    let mut bit = 0;
+
+    while bit < 32 {
+        directions[bit] = if degree == 0 {
+            1 << (31 - bit)
+        } else if bit < degree {
+            initial[bit] << (31 - bit)
+        } else {
+            let mut direction = directions[bit - degree] ^ (directions[bit - degree] >> degree);
+            let mut term = 1;
+            while term < degree {
+                if (coefficients >> (degree - 1 - term)) & 1 == 1 {
+                    direction ^= directions[bit - term];
+                }
+                term += 1;
+            }
+            direction
+        };
+        bit += 1;
+    }
+
+    directions
+}
+
+fn sobol(index: u32, dimension: usize) -> u32 {
+    let directions = &SOBOL_DIRECTIONS[dimension];
+    (0..32).filter(|bit| (index >> bit) & 1 == 1).fold(0, |value, bit| value ^ directions[bit])
+}
+
+// Owen scrambling, each bit is flipped or not depending on the bits above
+// it. Laine and Karras' hash does this for the bits below instead.
+fn nested_uniform_scramble(value: u32, seed: u32) -> u32 {
+    let mut value = value.reverse_bits();
+    value = value.wrapping_add(seed);
+    value ^= value.wrapping_mul(0x6c50_b47c);
+    value ^= value.wrapping_mul(0xb82f_1e52);
+    value ^= value.wrapping_mul(0xc7af_e638);
+    value ^= value.wrapping_mul(0x8d22_f6e6);
+    value.reverse_bits()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn samplers() -> Vec<Box<dyn Sampler>> {
+        vec![
+            Box::new(IndependentSampler),
+            Box::new(StratifiedSampler::new(4)),
+            Box::new(HaltonSampler),
+            Box::new(SobolSampler),
+        ]
+    }
+
+    #[test]
+    fn test_samples_are_reproducible_and_in_range() {
+        for sampler in samplers() {
+            for index in 0..64 {
+                for dimension in 0..50 {
+                    let value = sampler.get(7, 3, 5, index, dimension);
+                    assert!((0.0..1.0).contains(&value));
+                    assert_eq!(value.to_bits(), sampler.get(7, 3, 5, index, dimension).to_bits());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stratified_fills_every_cell() {
+        let sampler = StratifiedSampler::new(4);
+
+        for dimension in &[0, 2, 8] {
+            let mut cells = [0; 16];
+            for index in 0..16 {
+                let u = (sampler.get(1, 2, 3, index, *dimension) * 4.0) as usize;
+                let v = (sampler.get(1, 2, 3, index, dimension + 1) * 4.0) as usize;
+                cells[v * 4 + u] += 1;
+            }
+
+            assert_eq!(cells, [1; 16]);
+        }
+    }
+
+    #[test]
+    fn test_sobol_stratifies_powers_of_two() {
+        for dimension in 0..12 {
+            for count in &[2, 4, 16, 64] {
+                let mut intervals = vec![0; *count];
+                for index in 0..*count {
+                    intervals[(SobolSampler.get(1, 2, 3, index, dimension) * *count as f32) as usize] += 1;
+                }
+
+                assert_eq!(intervals, vec![1; *count], "dimension {}", dimension);
+            }
+        }
+    }
+
+    #[test]
+    fn test_halton_stratifies_powers_of_its_base() {
+        for &(dimension, count) in &[(0, 16), (1, 9), (2, 25)] {
+            let mut intervals = vec![0; count];
+            for index in 0..count {
+                intervals[(HaltonSampler.get(1, 2, 3, index, dimension) * count as f32) as usize] += 1;
+            }
+
+            assert_eq!(intervals, vec![1; count], "dimension {}", dimension);
+        }
+    }
+
+    #[test]
+    fn test_converges_faster_than_independent() {
+        // The area of a quarter disk, estimated separately in many pixels
+        let error = |sampler: &dyn Sampler| {
+            let mut squared_error = 0.0;
+            for pixel in 0..256 {
+                let hits = (0..16).filter(|&index| {
+                    let u = sampler.get(0, pixel, 0, index, 5);
+                    let v = sampler.get(0, pixel, 0, index, 6);
+                    u * u + v * v < 1.0
+                }).count();
+
+                let estimate = hits as f32 / 16.0;
+                squared_error += (estimate - ::std::f32::consts::FRAC_PI_4).powi(2);
+            }
+            squared_error
+        };
+
+        let independent = error(&IndependentSampler);
+        assert!(error(&StratifiedSampler::new(4)) < independent / 2.0);
+        assert!(error(&HaltonSampler) < independent / 2.0);
+        assert!(error(&SobolSampler) < independent / 2.0);
+    }
+
+    #[test]
+    fn test_rng_keeps_bounces_apart() {
+        let sampler = SobolSampler;
+        let mut rng = SamplerRng::new(&sampler, 0, 1, 2, 3);
+
+        // However many numbers the first bounce uses, the second starts at
+        // the same dimension
+        rng.start_bounce(0);
+        for _ in 0..10 {
+            rng.next_f32();
+        }
+        rng.start_bounce(1);
+
+        let expected = sampler.get(0, 1, 2, 3, FIRST_BOUNCE_DIMENSION + BOUNCE_DIMENSIONS + 1);
+        assert_eq!(rng.next_f32(), expected);
+    }
+
+    #[test]
+    fn test_rng_camera_dimensions() {
+        let sampler = SobolSampler;
+        let mut rng = SamplerRng::new(&sampler, 0, 1, 2, 3);
+
+        // Drawing too many numbers for the pixel doesn't move the lens
+        for _ in 0..3 {
+            rng.next_f32();
+        }
+        rng.start_lens();
+        assert_eq!(rng.next_f32(), sampler.get(0, 1, 2, 3, LENS_DIMENSION));
+
+        rng.start_time();
+        assert_eq!(rng.next_f32(), sampler.get(0, 1, 2, 3, TIME_DIMENSION));
+    }
+
+    #[test]
+    fn test_rng_integers() {
+        let sampler = SobolSampler;
+
+        // The top bits follow the sampler, and every bit varies
+        let mut odd = 0;
+        for index in 0..64 {
+            let value = SamplerRng::new(&sampler, 0, 1, 2, index).next_u32();
+            assert_eq!(value >> 8, (sampler.get(0, 1, 2, index, PIXEL_DIMENSION) * (1u32 << 24) as f32) as u32);
+            odd += value % 2;
+        }
+        assert!(odd > 16 && odd < 48);
+    }
+}
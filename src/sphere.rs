@@ -1,13 +1,16 @@
+use aabb::Aabb;
 use material::Material;
+use rand::Rng;
 use vector::Vector4;
 use ray::Ray;
 use renderable::{ IntersectionRecord, Renderable };
-use std::rc::Rc;
+use std::f32::consts::PI;
+use std::sync::Arc;
 
 pub struct Sphere {
     center: Vector4,
     radius: f32,
-    material: Rc<Box<Material>>,
+    material: Arc<dyn Material>,
 }
 
 impl Sphere {
@@ -15,50 +18,214 @@ impl Sphere {
         Sphere {
             center: center,
             radius: radius,
-            material: Rc::new(Box::new(material))
+            material: Arc::new(material)
         }
     }
 }
 
+/// A sphere whose centre moves in a straight line from `center0` at `time0`
+/// to `center1` at `time1`, for motion blur. Outside of that interval it
+/// rests at whichever end is nearer.
+pub struct MovingSphere {
+    center0: Vector4,
+    center1: Vector4,
+    time0: f32,
+    time1: f32,
+    radius: f32,
+    material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new<TMaterial: Material + 'static>(center0: Vector4, center1: Vector4,
+                                              time0: f32, time1: f32,
+                                              radius: f32, material: TMaterial) -> Self {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material: Arc::new(material),
+        }
+    }
+
+    pub fn center(&self, time: f32) -> Vector4 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+
+        let t = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + (self.center1 - self.center0) * t
+    }
+}
+
+/// Maps a point on the unit sphere to surface coordinates. `u` goes around
+/// the y axis starting from -x, `v` goes from the south pole to the north.
+fn sphere_uv(point: Vector4) -> (f32, f32) {
+    let theta = (-point.y).clamp(-1.0, 1.0).acos();
+    let phi = (-point.z).atan2(point.x) + PI;
+
+    (phi / (2.0 * PI), theta / PI)
+}
+
+/// The derivatives of the surface position with respect to the coordinates
+/// from `sphere_uv`, for a point on the unit sphere scaled by `radius`.
+fn sphere_tangents(point: Vector4, radius: f32) -> (Vector4, Vector4) {
+    let dpdu = Vector4::new3(point.z, 0.0, -point.x) * (2.0 * PI * radius);
+
+    // Distance from the y axis, zero at the poles where v's tangent lies
+    // along any line of longitude.
+    let sin_theta = (point.x * point.x + point.z * point.z).sqrt();
+    let dpdv = if sin_theta > 0.0 {
+        Vector4::new3(-point.x * point.y / sin_theta, sin_theta, -point.y * point.z / sin_theta) * (PI * radius)
+    } else {
+        Vector4::new3(-point.y, 0.0, 0.0) * (PI * radius)
+    };
+
+    (dpdu, dpdv)
+}
+
+/// The nearest distance within the range at which `ray` hits the sphere.
+fn hit_distance(center: Vector4, radius: f32, ray: &Ray, distance_min: f32, distance_max: f32) -> Option<f32> {
+    let o_minus_c = ray.origin() - center;
+
+    let a = ray.direction().dot3(ray.direction());
+    let b = o_minus_c.dot3(ray.direction());
+    let c = (o_minus_c).dot3(o_minus_c) - radius * radius;
+
+    let discriminant = b * b - a * c;
+
+    // Get the distance (d) value if a hit occurred ahead of the ray
+    if discriminant > 0.0 {
+        let sqrt_discriminant = discriminant.sqrt();
+        let distance = (-b - sqrt_discriminant) / a;
+
+        if distance < distance_max && distance > distance_min {
+            return Some(distance);
+        }
+
+        let distance = (-b + sqrt_discriminant) / a;
+        if distance < distance_max && distance > distance_min {
+            return Some(distance);
+        }
+    }
+
+    None
+}
+
+fn intersection_record(center: Vector4, radius: f32, material: &Arc<dyn Material>,
+                       ray: &Ray, distance: f32) -> IntersectionRecord {
+    let intersection_point = ray.point_at_distance(distance);
+
+    // A negative radius turns the sphere inside out, flipping the normal
+    let outward_normal = (intersection_point - center) / radius;
+    let unit_point = (intersection_point - center) / radius.abs();
+
+    IntersectionRecord::new(
+        ray,
+        distance,
+        outward_normal,
+        sphere_uv(unit_point),
+        sphere_tangents(unit_point, radius.abs()),
+        material.clone(),
+    )
+}
+
+fn bounding_box(center: Vector4, radius: f32) -> Aabb {
+    // A negative radius flips the normals but covers the same space
+    let radius = radius.abs();
+    let radius = Vector4::new3(radius, radius, radius);
+    Aabb::new(center - radius, center + radius)
+}
+
 impl Renderable for Sphere {
-    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32)
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, _rng: &mut dyn Rng)
         -> Option<IntersectionRecord> {
 
-        let o_minus_c = ray.origin() - self.center;
-
-        let a = ray.direction().dot3(ray.direction());
-        let b = o_minus_c.dot3(ray.direction());
-        let c = (o_minus_c).dot3(o_minus_c) - self.radius * self.radius;
-
-        let discriminant = b * b - a * c;
-
-        // Get the distance (d) value if a hit occurred ahead of the ray
-        if discriminant > 0.0 {
-            let sqrt_discriminant = discriminant.sqrt();
-            let distance = (-b - sqrt_discriminant) / a;
-
-            if distance < distance_max && distance > distance_min {
-                let intersection_point = ray.point_at_distance(distance);
-                return Some(IntersectionRecord::new(
-                        distance,
-                        intersection_point,
-                        (intersection_point - self.center) / self.radius,
-                        self.material.clone(),
-                    ));
-            }
-
-            let distance = (-b + sqrt_discriminant) / a;
-            if distance < distance_max && distance > distance_min {
-                let intersection_point = ray.point_at_distance(distance);
-                return Some(IntersectionRecord::new(
-                        distance,
-                        intersection_point,
-                        (intersection_point - self.center) / self.radius,
-                        self.material.clone(),
-                     ));
-            }
-        }
+        hit_distance(self.center, self.radius, ray, distance_min, distance_max)
+            .map(|distance| intersection_record(self.center, self.radius, &self.material, ray, distance))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(bounding_box(self.center, self.radius))
+    }
+}
+
+impl Renderable for MovingSphere {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, _rng: &mut dyn Rng)
+        -> Option<IntersectionRecord> {
+
+        let center = self.center(ray.time());
+        hit_distance(center, self.radius, ray, distance_min, distance_max)
+            .map(|distance| intersection_record(center, self.radius, &self.material, ray, distance))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The centre moves in a straight line, so the boxes at either end
+        // enclose every position in between
+        Some(Aabb::surrounding(&bounding_box(self.center0, self.radius),
+                               &bounding_box(self.center1, self.radius)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use random::seeded_rng;
+
+    fn unit_sphere() -> Sphere {
+        Sphere::new(Vector4::new3(0.0, 0.0, 0.0), 1.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)))
+    }
+
+    #[test]
+    fn test_front_face() {
+        let sphere = unit_sphere();
+
+        let outside = Ray::new(Vector4::new3(0.0, 0.0, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        let record = sphere.intersects(&outside, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert!(record.front_face);
+        assert_eq!(record.normal.z, 1.0);
+
+        let inside = Ray::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 0.0, -1.0));
+        let record = sphere.intersects(&inside, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert!(!record.front_face);
+        assert_eq!(record.normal.z, 1.0);
+    }
+
+    #[test]
+    fn test_surface_coordinates() {
+        let sphere = unit_sphere();
+
+        let ray = Ray::new(Vector4::new3(0.0, 0.5, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        let record = sphere.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+
+        // Facing +z is a quarter of the way around from -x
+        assert!((record.u - 0.25).abs() < 1e-5);
+        assert!(record.v > 0.5 && record.v < 1.0);
+
+        // The tangents lie in the surface and point along increasing u and v
+        assert!(record.dpdu.dot3(record.normal).abs() < 1e-5);
+        assert!(record.dpdv.dot3(record.normal).abs() < 1e-5);
+        assert!(record.dpdu.x > 0.0);
+        assert!(record.dpdv.y > 0.0);
+    }
+
+    #[test]
+    fn test_moving_sphere() {
+        let sphere = MovingSphere::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(4.0, 0.0, 0.0), 0.0, 1.0,
+                                       1.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5)));
+
+        // Halfway through the motion the sphere sits at x = 2
+        let direction = Vector4::new3(0.0, 0.0, -1.0);
+        assert!(sphere.intersects(&Ray::with_time(Vector4::new3(2.0, 0.0, 5.0), direction, 0.5), 0.001, f32::MAX, &mut seeded_rng(0)).is_some());
+        assert!(sphere.intersects(&Ray::with_time(Vector4::new3(2.0, 0.0, 5.0), direction, 0.0), 0.001, f32::MAX, &mut seeded_rng(0)).is_none());
+
+        // Beyond the end of the motion it stays put
+        assert!(sphere.intersects(&Ray::with_time(Vector4::new3(4.0, 0.0, 5.0), direction, 2.0), 0.001, f32::MAX, &mut seeded_rng(0)).is_some());
 
-        None
+        let bounding_box = sphere.bounding_box().unwrap();
+        assert_eq!(bounding_box.min.x, -1.0);
+        assert_eq!(bounding_box.max.x, 5.0);
     }
 }
@@ -0,0 +1,241 @@
+use rand::Rng;
+use random::seeded_rng;
+use std::sync::Arc;
+use vector::Vector4;
+
+/// A colour which varies over a surface, looked up by the surface
+/// coordinates and position of a hit.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f32, v: f32, point: Vector4) -> Vector4;
+}
+
+/// The same colour everywhere.
+#[derive(Clone)]
+pub struct ConstantTexture {
+    colour: Vector4,
+}
+
+impl ConstantTexture {
+    pub fn new(colour: Vector4) -> Self {
+        ConstantTexture {
+            colour,
+        }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _: f32, _: f32, _: Vector4) -> Vector4 {
+        self.colour
+    }
+}
+
+/// A three dimensional checkerboard, alternating between two textures in
+/// cubes whose size is set by `frequency`.
+///
+/// Being solid rather than mapped onto the surface, it never stretches or
+/// leaves seams.
+#[derive(Clone)]
+pub struct CheckerTexture {
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
+    frequency: f32,
+}
+
+impl CheckerTexture {
+    pub fn new<TOdd: Texture + 'static, TEven: Texture + 'static>(odd: TOdd, even: TEven, frequency: f32) -> Self {
+        CheckerTexture {
+            odd: Arc::new(odd),
+            even: Arc::new(even),
+            frequency,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f32, v: f32, point: Vector4) -> Vector4 {
+        let sines = (self.frequency * point.x).sin() * (self.frequency * point.y).sin() * (self.frequency * point.z).sin();
+        if sines < 0.0 {
+            self.odd.value(u, v, point)
+        } else {
+            self.even.value(u, v, point)
+        }
+    }
+}
+
+const PERLIN_POINT_COUNT: usize = 256;
+
+/// Perlin gradient noise, from chapter 4 of "Ray Tracing: The Next Week".
+///
+/// Random unit vectors sit on the corners of a lattice, and are blended with
+/// a smoothed trilinear interpolation so the noise has no blocky artefacts.
+pub struct Perlin {
+    gradients: Vec<Vector4>,
+    permute_x: Vec<usize>,
+    permute_y: Vec<usize>,
+    permute_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Creates the noise, the same seed always gives the same noise.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = seeded_rng(seed);
+
+        let gradients = (0..PERLIN_POINT_COUNT).map(|_| {
+            Vector4::new3(
+                rng.next_f32() * 2.0 - 1.0,
+                rng.next_f32() * 2.0 - 1.0,
+                rng.next_f32() * 2.0 - 1.0,
+            ).unit_vector()
+        }).collect();
+
+        Perlin {
+            gradients,
+            permute_x: permutation(&mut rng),
+            permute_y: permutation(&mut rng),
+            permute_z: permutation(&mut rng),
+        }
+    }
+
+    /// The noise at `point`, between -1.0 and 1.0.
+    pub fn noise(&self, point: Vector4) -> f32 {
+        let (fx, fy, fz) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let (u, v, w) = (point.x - fx, point.y - fy, point.z - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        // Hermite cubic smoothing of the interpolation weights
+        let (uu, vv, ww) = (u * u * (3.0 - 2.0 * u), v * v * (3.0 - 2.0 * v), w * w * (3.0 - 2.0 * w));
+
+        let mut accumulated = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[
+                        self.permute_x[wrap(i + di)] ^ self.permute_y[wrap(j + dj)] ^ self.permute_z[wrap(k + dk)]
+                    ];
+
+                    let (fi, fj, fk) = (di as f32, dj as f32, dk as f32);
+                    let weight = Vector4::new3(u - fi, v - fj, w - fk);
+                    accumulated += (fi * uu + (1.0 - fi) * (1.0 - uu)) *
+                                   (fj * vv + (1.0 - fj) * (1.0 - vv)) *
+                                   (fk * ww + (1.0 - fk) * (1.0 - ww)) *
+                                   gradient.dot3(weight);
+                }
+            }
+        }
+
+        accumulated
+    }
+
+    /// Sums `depth` octaves of noise, each at double the frequency and half
+    /// the weight of the last.
+    pub fn turbulence(&self, point: Vector4, depth: u32) -> f32 {
+        let mut accumulated = 0.0;
+        let mut point = point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accumulated += weight * self.noise(point);
+            weight *= 0.5;
+            point *= 2.0;
+        }
+
+        accumulated.abs()
+    }
+}
+
+fn permutation(rng: &mut dyn Rng) -> Vec<usize> {
+    let mut permutation: Vec<usize> = (0..PERLIN_POINT_COUNT).collect();
+
+    // Fisher-Yates shuffle
+    for i in (1..PERLIN_POINT_COUNT).rev() {
+        let target = (rng.next_u32() as usize) % (i + 1);
+        permutation.swap(i, target);
+    }
+
+    permutation
+}
+
+fn wrap(index: i64) -> usize {
+    (index & (PERLIN_POINT_COUNT as i64 - 1)) as usize
+}
+
+/// The octaves of turbulence summed by the turbulent and marble textures.
+const TURBULENCE_DEPTH: u32 = 7;
+
+/// How a `NoiseTexture` turns Perlin noise into a colour.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Smoothly varying noise
+    Smooth,
+
+    /// Several octaves of noise, like a net or camouflage
+    Turbulence,
+
+    /// Stripes along z, their phase disturbed by turbulence
+    Marble,
+}
+
+/// A grey procedural texture built from Perlin noise, `scale` sets how
+/// quickly it varies across space.
+pub struct NoiseTexture {
+    noise: Perlin,
+    kind: NoiseKind,
+    scale: f32,
+}
+
+impl NoiseTexture {
+    pub fn new(kind: NoiseKind, scale: f32, seed: u64) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(seed),
+            kind,
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _: f32, _: f32, point: Vector4) -> Vector4 {
+        let white = Vector4::new3(1.0, 1.0, 1.0);
+        let scaled = point * self.scale;
+
+        match self.kind {
+            NoiseKind::Smooth => white * 0.5 * (1.0 + self.noise.noise(scaled)),
+            NoiseKind::Turbulence => white * self.noise.turbulence(scaled, TURBULENCE_DEPTH),
+            NoiseKind::Marble => {
+                white * 0.5 * (1.0 + (scaled.z + 10.0 * self.noise.turbulence(point, TURBULENCE_DEPTH)).sin())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let checker = CheckerTexture::new(
+            ConstantTexture::new(Vector4::new3(0.0, 0.0, 0.0)),
+            ConstantTexture::new(Vector4::new3(1.0, 1.0, 1.0)),
+            1.0);
+
+        assert_eq!(checker.value(0.0, 0.0, Vector4::new3(1.0, 1.0, 1.0)).x, 1.0);
+        assert_eq!(checker.value(0.0, 0.0, Vector4::new3(-1.0, 1.0, 1.0)).x, 0.0);
+    }
+
+    #[test]
+    fn test_noise_range() {
+        let perlin = Perlin::new(1);
+        let mut rng = seeded_rng(2);
+
+        for _ in 0..1000 {
+            let point = Vector4::new3(rng.next_f32() * 100.0, rng.next_f32() * 100.0, rng.next_f32() * 100.0);
+            let noise = perlin.noise(point);
+            assert!((-1.0..=1.0).contains(&noise));
+        }
+
+        // The same seed gives the same noise
+        let point = Vector4::new3(1.5, 2.25, 3.125);
+        assert_eq!(perlin.noise(point), Perlin::new(1).noise(point));
+    }
+}
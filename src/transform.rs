@@ -0,0 +1,137 @@
+use aabb::Aabb;
+use rand::Rng;
+use vector::{ Matrix4, Vector4 };
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+
+/// An instance of a renderable placed in the scene by an affine transform,
+/// mapping its own object space into world space.
+///
+/// Wrap an `Arc` of the renderable to place the same geometry many times
+/// without copying it.
+pub struct Transformed<TRenderable: Renderable> {
+    renderable: TRenderable,
+    object_to_world: Matrix4,
+    world_to_object: Matrix4,
+
+    /// The inverse transpose, which keeps normals perpendicular to the
+    /// surface under non-uniform scales and shears
+    normal_to_world: Matrix4,
+}
+
+impl<TRenderable: Renderable> Transformed<TRenderable> {
+    /// Panics if the transform can't be inverted, e.g. a scale of zero.
+    pub fn new(renderable: TRenderable, transform: Matrix4) -> Self {
+        let inverse = transform.inverse().expect("instance transform is singular");
+
+        Transformed {
+            renderable,
+            object_to_world: transform,
+            world_to_object: inverse,
+            normal_to_world: inverse.transpose(),
+        }
+    }
+
+    pub fn transform(&self) -> Matrix4 {
+        self.object_to_world
+    }
+}
+
+impl<TRenderable: Renderable> Renderable for Transformed<TRenderable> {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        // The direction isn't renormalised, so distances along the object
+        // space ray are the same as along the world space ray
+        let object_ray = Ray::with_time(
+            self.world_to_object.transform_point(ray.origin()),
+            self.world_to_object.transform_vector(ray.direction()),
+            ray.time(),
+        );
+
+        let record = self.renderable.intersects(&object_ray, distance_min, distance_max, rng)?;
+
+        let normal = self.normal_to_world.transform_vector(record.normal).unit_vector();
+        let outward_normal = if record.front_face { normal } else { -normal };
+
+        Some(IntersectionRecord::new(
+            ray,
+            record.distance,
+            outward_normal,
+            (record.u, record.v),
+            (self.object_to_world.transform_vector(record.dpdu), self.object_to_world.transform_vector(record.dpdv)),
+            record.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let object_box = self.renderable.bounding_box()?;
+
+        // Surround all eight transformed corners of the object's box
+        let corner = |i: usize| Vector4::new3(
+            if i & 1 == 0 { object_box.min.x } else { object_box.max.x },
+            if i & 2 == 0 { object_box.min.y } else { object_box.max.y },
+            if i & 4 == 0 { object_box.min.z } else { object_box.max.z },
+        );
+
+        let first = self.object_to_world.transform_point(corner(0));
+        let world_box = (1..8).fold(Aabb::new(first, first), |world_box, i| {
+            world_box.include(self.object_to_world.transform_point(corner(i)))
+        });
+
+        Some(world_box)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use sphere::Sphere;
+    use std::sync::Arc;
+    use random::seeded_rng;
+
+    fn unit_sphere() -> Arc<Sphere> {
+        Arc::new(Sphere::new(Vector4::new3(0.0, 0.0, 0.0), 1.0, Lambertian::new(Vector4::new3(0.5, 0.5, 0.5))))
+    }
+
+    #[test]
+    fn test_translated_instances() {
+        let sphere = unit_sphere();
+        let left = Transformed::new(sphere.clone(), Matrix4::translation(Vector4::new3(-3.0, 0.0, 0.0)));
+        let right = Transformed::new(sphere, Matrix4::translation(Vector4::new3(3.0, 0.0, 0.0)));
+
+        let ray = Ray::new(Vector4::new3(3.0, 0.0, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        assert!(left.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).is_none());
+
+        let record = right.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert!((record.distance - 4.0).abs() < 1e-5);
+        assert!((record.intersection_point.x - 3.0).abs() < 1e-5);
+        assert!((record.normal.z - 1.0).abs() < 1e-5);
+        assert!(record.front_face);
+    }
+
+    #[test]
+    fn test_scaled_normals() {
+        // An ellipsoid twice as wide as it is tall
+        let ellipsoid = Transformed::new(unit_sphere(), Matrix4::scale(Vector4::new3(2.0, 1.0, 1.0)));
+
+        // Hits the unit sphere at (0.6, 0.48, 0.64) in object space
+        let ray = Ray::new(Vector4::new3(1.2, 0.48, 5.0), Vector4::new3(0.0, 0.0, -1.0));
+        let record = ellipsoid.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+
+        // The gradient of x²/4 + y² + z² at the hit point
+        let expected = Vector4::new3(0.6, 0.96, 1.28).unit_vector();
+        assert!((record.normal - expected).length() < 1e-4);
+        assert!(record.dpdu.dot3(record.normal).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_rotated_bounding_box() {
+        let rotated = Transformed::new(unit_sphere(), Matrix4::rotation(Vector4::new3(0.0, 1.0, 0.0), ::std::f32::consts::FRAC_PI_4));
+
+        let bounding_box = rotated.bounding_box().unwrap();
+        let extent = 2.0_f32.sqrt();
+        assert!((bounding_box.max.x - extent).abs() < 1e-5);
+        assert!((bounding_box.min.z + extent).abs() < 1e-5);
+        assert!((bounding_box.max.y - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,369 @@
+use aabb::Aabb;
+use bvh::Bvh;
+use material::Material;
+use rand::Rng;
+use ray::Ray;
+use renderable::{ IntersectionRecord, Renderable };
+use std::sync::Arc;
+use vector::Vector4;
+
+/// Flat triangles have no thickness along one axis, their boxes are padded
+/// so that they still have some volume.
+const BOUNDING_BOX_PADDING: f32 = 0.0001;
+
+/// The vertex and index buffers shared by every triangle in a mesh.
+struct MeshData {
+    positions: Vec<Vector4>,
+    normals: Option<Vec<Vector4>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    indices: Vec<[usize; 3]>,
+    material: Arc<dyn Material>,
+}
+
+/// A single triangle of a mesh, referring to its vertices by index.
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    index: usize,
+}
+
+impl Triangle {
+    /// Creates a standalone triangle, the vertices wind anticlockwise around
+    /// the front face.
+    pub fn new<TMaterial: Material + 'static>(a: Vector4, b: Vector4, c: Vector4, material: TMaterial) -> Self {
+        Triangle {
+            mesh: Arc::new(MeshData {
+                positions: vec![a, b, c],
+                normals: None,
+                uvs: None,
+                indices: vec![[0, 1, 2]],
+                material: Arc::new(material),
+            }),
+            index: 0,
+        }
+    }
+
+    fn vertices(&self) -> [usize; 3] {
+        self.mesh.indices[self.index]
+    }
+
+    /// Interpolates the vertex texture coordinates, without them the
+    /// barycentric coordinates of the second and third vertices are used.
+    fn uv(&self, barycentric: [f32; 3]) -> (f32, f32) {
+        let [i0, i1, i2] = self.vertices();
+
+        match self.mesh.uvs {
+            Some(ref uvs) => (
+                uvs[i0].0 * barycentric[0] + uvs[i1].0 * barycentric[1] + uvs[i2].0 * barycentric[2],
+                uvs[i0].1 * barycentric[0] + uvs[i1].1 * barycentric[1] + uvs[i2].1 * barycentric[2],
+            ),
+            None => (barycentric[1], barycentric[2]),
+        }
+    }
+
+    /// Solves for the derivatives of position with respect to `u` and `v`,
+    /// which are constant across a triangle.
+    fn tangents(&self, normal: Vector4) -> (Vector4, Vector4) {
+        let [i0, i1, i2] = self.vertices();
+        let positions = &self.mesh.positions;
+
+        let (uv0, uv1, uv2) = match self.mesh.uvs {
+            Some(ref uvs) => (uvs[i0], uvs[i1], uvs[i2]),
+            None => ((0.0, 0.0), (1.0, 0.0), (0.0, 1.0)),
+        };
+
+        let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let dp02 = positions[i0] - positions[i2];
+        let dp12 = positions[i1] - positions[i2];
+
+        let determinant = du02 * dv12 - dv02 * du12;
+        if determinant.abs() < 1e-12 {
+            // Degenerate texture coordinates, any tangents will do
+            return coordinate_system(normal);
+        }
+
+        let inverse = 1.0 / determinant;
+        ((dp02 * dv12 - dp12 * dv02) * inverse, (dp12 * du02 - dp02 * du12) * inverse)
+    }
+}
+
+/// Creates two unit vectors perpendicular to `normal` and each other.
+pub(crate) fn coordinate_system(normal: Vector4) -> (Vector4, Vector4) {
+    let tangent = if normal.x.abs() > normal.y.abs() {
+        Vector4::new3(-normal.z, 0.0, normal.x).unit_vector()
+    } else {
+        Vector4::new3(0.0, normal.z, -normal.y).unit_vector()
+    };
+
+    (tangent, normal.cross(tangent))
+}
+
+impl Renderable for Triangle {
+    /// A watertight ray/triangle test, as described in "Watertight
+    /// Ray/Triangle Intersection" (Woop, Benthin and Wald, 2013).
+    ///
+    /// Rays passing exactly along a shared edge hit one of the triangles
+    /// either side of it, they never slip through the gap.
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, _rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        let [i0, i1, i2] = self.vertices();
+        let positions = &self.mesh.positions;
+        let direction = ray.direction();
+
+        // Shear and scale the vertices into a space where the ray starts at
+        // the origin and travels along +z.
+        let kz = dominant_axis(direction);
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        if direction[kz] < 0.0 {
+            ::std::mem::swap(&mut kx, &mut ky);
+        }
+
+        let shear_x = direction[kx] / direction[kz];
+        let shear_y = direction[ky] / direction[kz];
+        let shear_z = 1.0 / direction[kz];
+
+        let a = positions[i0] - ray.origin();
+        let b = positions[i1] - ray.origin();
+        let c = positions[i2] - ray.origin();
+
+        let ax = a[kx] - shear_x * a[kz];
+        let ay = a[ky] - shear_y * a[kz];
+        let bx = b[kx] - shear_x * b[kz];
+        let by = b[ky] - shear_y * b[kz];
+        let cx = c[kx] - shear_x * c[kz];
+        let cy = c[ky] - shear_y * c[kz];
+
+        // Scaled barycentric coordinates, the edge functions
+        let mut u = cx * by - cy * bx;
+        let mut v = ax * cy - ay * cx;
+        let mut w = bx * ay - by * ax;
+
+        // Fall back to double precision on an edge, so that neighbouring
+        // triangles agree on which of them was hit.
+        if u == 0.0 || v == 0.0 || w == 0.0 {
+            u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+            v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+            w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+        }
+
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+            return None;
+        }
+
+        let determinant = u + v + w;
+        if determinant == 0.0 {
+            return None;
+        }
+
+        let az = shear_z * a[kz];
+        let bz = shear_z * b[kz];
+        let cz = shear_z * c[kz];
+        let distance = (u * az + v * bz + w * cz) / determinant;
+
+        if distance <= distance_min || distance >= distance_max {
+            return None;
+        }
+
+        let barycentric = [u / determinant, v / determinant, w / determinant];
+        let edge_1 = positions[i1] - positions[i0];
+        let edge_2 = positions[i2] - positions[i0];
+        let geometric_normal = edge_1.cross(edge_2).unit_vector();
+
+        // The shading normal is kept on the same side as the geometric
+        // normal, which decides which face was hit.
+        let outward_normal = match self.mesh.normals {
+            Some(ref normals) => {
+                let shading_normal =
+                    (normals[i0] * barycentric[0] + normals[i1] * barycentric[1] + normals[i2] * barycentric[2]).unit_vector();
+                if shading_normal.dot3(geometric_normal) < 0.0 { -shading_normal } else { shading_normal }
+            }
+            None => geometric_normal,
+        };
+
+        let mut record = IntersectionRecord::new(
+            ray,
+            distance,
+            outward_normal,
+            self.uv(barycentric),
+            self.tangents(geometric_normal),
+            self.mesh.material.clone(),
+        );
+
+        // Flip against the geometric normal, an interpolated normal can
+        // disagree with it near the silhouette.
+        record.front_face = ray.direction().dot3(geometric_normal) < 0.0;
+        record.normal = if record.front_face { outward_normal } else { -outward_normal };
+
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [i0, i1, i2] = self.vertices();
+        let positions = &self.mesh.positions;
+        let padding = Vector4::new3(BOUNDING_BOX_PADDING, BOUNDING_BOX_PADDING, BOUNDING_BOX_PADDING);
+
+        let bounding_box = Aabb::new(positions[i0], positions[i0])
+            .include(positions[i1])
+            .include(positions[i2]);
+
+        Some(Aabb::new(bounding_box.min - padding, bounding_box.max + padding))
+    }
+}
+
+fn dominant_axis(vector: Vector4) -> usize {
+    let (x, y, z) = (vector.x.abs(), vector.y.abs(), vector.z.abs());
+    if x > y && x > z {
+        0
+    } else if y > z {
+        1
+    } else {
+        2
+    }
+}
+
+/// An indexed triangle mesh, every triangle shares the mesh's vertex
+/// buffers and material.
+///
+/// When per-vertex normals are given they are interpolated across each
+/// triangle for smooth shading, otherwise triangles are flat shaded. Cloning
+/// a mesh shares its buffers rather than copying them.
+#[derive(Clone)]
+pub struct TriangleMesh {
+    mesh: Arc<MeshData>,
+    triangles: Arc<Bvh>,
+}
+
+impl TriangleMesh {
+    /// Creates a mesh from its vertex buffers, `normals` and `uvs` must have
+    /// one entry per position. Each entry in `indices` is a triangle,
+    /// winding anticlockwise around its front face.
+    ///
+    /// Panics if the buffers don't match up.
+    pub fn new<TMaterial: Material + 'static>(positions: Vec<Vector4>, normals: Option<Vec<Vector4>>,
+                                              uvs: Option<Vec<(f32, f32)>>, indices: Vec<[usize; 3]>,
+                                              material: TMaterial) -> Self {
+        TriangleMesh::with_shared_material(positions, normals, uvs, indices, Arc::new(material))
+    }
+
+    /// Creates a mesh using a material that may also be used elsewhere.
+    pub fn with_shared_material(positions: Vec<Vector4>, normals: Option<Vec<Vector4>>,
+                                uvs: Option<Vec<(f32, f32)>>, indices: Vec<[usize; 3]>,
+                                material: Arc<dyn Material>) -> Self {
+        if let Some(ref normals) = normals {
+            assert_eq!(normals.len(), positions.len(), "one normal is needed per vertex position");
+        }
+
+        if let Some(ref uvs) = uvs {
+            assert_eq!(uvs.len(), positions.len(), "one uv is needed per vertex position");
+        }
+
+        assert!(indices.iter().all(|triangle| triangle.iter().all(|&index| index < positions.len())),
+                "triangle index out of range");
+
+        let mesh = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            indices,
+            material,
+        });
+
+        let triangles = (0..mesh.indices.len())
+            .map(|index| Box::new(Triangle { mesh: mesh.clone(), index }) as Box<dyn Renderable>)
+            .collect();
+
+        TriangleMesh {
+            mesh,
+            triangles: Arc::new(Bvh::new(triangles)),
+        }
+    }
+
+    pub fn positions(&self) -> &[Vector4] {
+        &self.mesh.positions
+    }
+
+    pub fn normals(&self) -> Option<&[Vector4]> {
+        self.mesh.normals.as_ref().map(|normals| &normals[..])
+    }
+
+    pub fn uvs(&self) -> Option<&[(f32, f32)]> {
+        self.mesh.uvs.as_ref().map(|uvs| &uvs[..])
+    }
+
+    pub fn indices(&self) -> &[[usize; 3]] {
+        &self.mesh.indices
+    }
+}
+
+impl Renderable for TriangleMesh {
+    fn intersects(&self, ray: &Ray, distance_min: f32, distance_max: f32, rng: &mut dyn Rng) -> Option<IntersectionRecord> {
+        self.triangles.intersects(ray, distance_min, distance_max, rng)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.triangles.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use material::Lambertian;
+    use random::seeded_rng;
+
+    fn grey() -> Lambertian {
+        Lambertian::new(Vector4::new3(0.5, 0.5, 0.5))
+    }
+
+    #[test]
+    fn test_triangle_hit() {
+        let triangle = Triangle::new(Vector4::new3(-1.0, -1.0, -2.0), Vector4::new3(1.0, -1.0, -2.0),
+                                     Vector4::new3(0.0, 1.0, -2.0), grey());
+
+        let ray = Ray::new(Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(0.0, 0.0, -1.0));
+        let record = triangle.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert_eq!(record.distance, 2.0);
+        assert_eq!(record.normal.z, 1.0);
+        assert!(record.front_face);
+
+        let behind = Ray::new(Vector4::new3(0.0, 0.0, -4.0), Vector4::new3(0.0, 0.0, 1.0));
+        let record = triangle.intersects(&behind, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap();
+        assert_eq!(record.normal.z, -1.0);
+        assert!(!record.front_face);
+
+        let miss = Ray::new(Vector4::new3(2.0, 0.0, 0.0), Vector4::new3(0.0, 0.0, -1.0));
+        assert!(triangle.intersects(&miss, 0.001, f32::MAX, &mut seeded_rng(0)).is_none());
+    }
+
+    #[test]
+    fn test_shared_edge_is_watertight() {
+        // Two triangles forming a square, split along the diagonal
+        let mesh = TriangleMesh::new(
+            vec![Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(1.0, 0.0, 0.0),
+                 Vector4::new3(1.0, 1.0, 0.0), Vector4::new3(0.0, 1.0, 0.0)],
+            None, None, vec![[0, 1, 2], [0, 2, 3]], grey());
+
+        for i in 1..100 {
+            let t = i as f32 / 100.0;
+            let ray = Ray::new(Vector4::new3(t, t, 1.0), Vector4::new3(0.0, 0.0, -1.0));
+            assert!(mesh.intersects(&ray, 0.001, f32::MAX, &mut seeded_rng(0)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_interpolated_normal() {
+        let up = Vector4::new3(0.0, 0.0, 1.0);
+        let tilted = Vector4::new3(1.0, 0.0, 1.0).unit_vector();
+        let mesh = TriangleMesh::new(
+            vec![Vector4::new3(0.0, 0.0, 0.0), Vector4::new3(1.0, 0.0, 0.0), Vector4::new3(0.0, 1.0, 0.0)],
+            Some(vec![up, tilted, up]), None, vec![[0, 1, 2]], grey());
+
+        let near_tilted = Ray::new(Vector4::new3(0.9, 0.05, 1.0), Vector4::new3(0.0, 0.0, -1.0));
+        let near_up = Ray::new(Vector4::new3(0.05, 0.05, 1.0), Vector4::new3(0.0, 0.0, -1.0));
+
+        let tilted_normal = mesh.intersects(&near_tilted, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap().normal;
+        let up_normal = mesh.intersects(&near_up, 0.001, f32::MAX, &mut seeded_rng(0)).unwrap().normal;
+        assert!(tilted_normal.x > up_normal.x);
+        assert!((tilted_normal.length() - 1.0).abs() < 1e-5);
+    }
+}